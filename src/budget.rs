@@ -0,0 +1,113 @@
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Limits on the work a resolution may do.
+#[derive(Clone, Debug, Default)]
+pub struct Budget {
+    /// Maximum number of nodes to visit.
+    pub steps: Option<usize>,
+    pub deadline: Option<Instant>,
+    /// Set to `true`, from any thread, to stop the resolution.
+    pub cancelled: Arc<AtomicBool>
+}
+
+impl Budget {
+    /// Budget without any limit.
+    pub fn new() -> Self {
+        Budget {
+            steps: None,
+            deadline: None,
+            cancelled: Arc::new(AtomicBool::new(false))
+        }
+    }
+
+    pub fn steps(mut self, steps: usize) -> Self {
+        self.steps = Some(steps);
+        self
+    }
+
+    pub fn deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Deadline `timeout` from now.
+    pub fn timeout(self, timeout: Duration) -> Self {
+        self.deadline(Instant::now() + timeout)
+    }
+
+    /// Stops when `flag` is set.
+    pub fn cancellable(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.cancelled = flag;
+        self
+    }
+
+    /// Why a resolution that took `steps` steps should stop, if it should.
+    pub fn exceeded(&self, steps: usize) -> Option<Reason> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return Some(Reason::Cancelled);
+        }
+
+        match (self.steps, self.deadline) {
+            (Some(max), _) if steps > max => Some(Reason::Steps),
+            (_, Some(deadline)) if Instant::now() >= deadline => Some(Reason::Deadline),
+            _ => None
+        }
+    }
+}
+
+/// Why a resolution was aborted.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Reason {
+    Steps,
+    Deadline,
+    Cancelled
+}
+
+/// Work done by a resolution.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Stats {
+    /// Nodes visited
+    pub steps: usize,
+    /// Nodes rejected as conflicting with their path
+    pub conflicts: usize,
+    /// Subproblems answered from the cache
+    pub cache_hits: usize,
+    pub elapsed: Duration
+}
+
+/// Resolution stopped before it could conclude.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Aborted {
+    pub reason: Reason,
+    /// Work done up to the abort
+    pub stats: Stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exceeds_limits() {
+        assert_eq!(Budget::new().exceeded(1_000_000), None);
+
+        let budget = Budget::new().steps(10);
+        assert_eq!(budget.exceeded(10), None);
+        assert_eq!(budget.exceeded(11), Some(Reason::Steps));
+
+        let past = Budget::new().timeout(Duration::from_secs(0));
+        assert_eq!(past.exceeded(0), Some(Reason::Deadline));
+
+        let future = Budget::new().timeout(Duration::from_secs(3600));
+        assert_eq!(future.exceeded(0), None);
+
+        let flag = Arc::new(AtomicBool::new(false));
+        let cancellable = Budget::new().steps(10).cancellable(flag.clone());
+        flag.store(true, Ordering::Relaxed);
+        assert_eq!(cancellable.exceeded(0), Some(Reason::Cancelled));
+    }
+}
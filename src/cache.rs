@@ -1,6 +1,266 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use node::Node;
+use node::cause::Cause;
+use node::resolvable::Resolvable;
+use node::resolved::Resolved;
+use package::ident::Ident;
+use path::Path;
+
+type Key<'a, R> = (&'a Node<R>, Vec<&'a Node<R>>);
+
+/// Ids a subtree may visit.
 #[derive(Debug)]
-pub struct Cache {
+struct Reachable<Id> {
+    ids: HashSet<Id>,
+    /// Conflict classes of the ids, if they all have one
+    classes: Option<HashSet<Id>>,
+    /// Ids the nodes of the subtree declare they can't be selected with
+    excluded: HashSet<Id>
+}
+
+impl<Id: Ident> Reachable<Id> {
+    fn conflicts_with(&self, id: &Id) -> bool {
+        match (&self.classes, id.conflict_class()) {
+            (Some(classes), Some(class)) => classes.contains(&class),
+            _ => self.ids
+                .iter()
+                .any(|other| Id::are_conflicting(&[id.clone(), other.clone()]))
+        }
+    }
+
+    /// Checks if `node` declares it can't be selected with some node of the
+    /// subtree, or the other way around.
+    fn excludes<R: Resolvable<Id = Id>>(&self, node: &Node<R>) -> bool {
+        self.excluded.contains(&node.id)
+            || node.exclusions().iter().any(|id| self.ids.contains(id))
+    }
+}
+
+#[derive(Debug)]
+struct Entry<'a, R: 'a + Resolvable> {
+    suffixes: Vec<Path<'a, R>>,
+    cause: Cause<'a, R>
+}
+
+/// Memoized results of solved subproblems.
+///
+/// The result of solving a node only depends on the part of the path its
+/// subtree can conflict with, so entries are keyed by the node and that part
+/// alone. Results are stored relative to the path they were solved under and
+/// re-attached to the current one on a hit.
+///
+/// Assumes `Ident::are_conflicting` is decided pairwise. Nodes declaring
+/// exclusions are part of the key on both sides. Results closing cycles
+/// through the path above the node depend on all of it, so `Node::solve_in`
+/// doesn't store them.
+#[derive(Debug)]
+pub struct Cache<'a, R: 'a + Resolvable> {
+    entries: HashMap<Key<'a, R>, Entry<'a, R>>,
+    reachable: HashMap<&'a Node<R>, Reachable<R::Id>>,
+    hits: usize
+}
+
+impl<'a, R: 'a + Resolvable> Cache<'a, R> {
+    pub fn new() -> Self {
+        Cache {
+            entries: HashMap::new(),
+            reachable: HashMap::new(),
+            hits: 0
+        }
+    }
+
+    /// Number of stored subproblems.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Number of lookups answered from the cache.
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// Checks if the last node of `path` was already solved under an
+    /// equivalent prefix.
+    pub fn contains(&mut self, path: &Path<'a, R>) -> bool {
+        let key = self.key(path);
+        self.entries.contains_key(&key)
+    }
+
+    /// Result of solving the last node of `path`, rebased onto its prefix.
+    pub fn get(&mut self, path: &Path<'a, R>) -> Option<Resolved<'a, R>> {
+        let key = self.key(path);
+        let prefix = Self::prefix(path);
+
+        let resolved = self.entries
+            .get(&key)
+            .map(|entry| {
+                let paths = entry.suffixes
+                    .iter()
+                    .map(|suffix| Path::chain(vec![prefix.clone(), suffix.clone()]))
+                    .collect();
+                Resolved::new(paths, entry.cause.clone())
+            });
+
+        if resolved.is_some() {
+            self.hits += 1;
+        }
+
+        resolved
+    }
+
+    /// Stores the result of solving the last node of `path`.
+    pub fn insert(&mut self, path: &Path<'a, R>, resolved: &Resolved<'a, R>) {
+        let key = self.key(path);
+        let prefix = Self::prefix(path);
+
+        let entry = Entry {
+            suffixes: resolved.paths
+                .iter()
+                .map(|p| p.suffix(&prefix))
+                .collect(),
+            cause: resolved.cause.clone()
+        };
+
+        self.entries.insert(key, entry);
+    }
+
+    fn prefix(path: &Path<'a, R>) -> Path<'a, R> {
+        let len = path.nodes.len() - 1;
+        Path::new(path.nodes[..len].to_vec())
+    }
+
+    fn key(&mut self, path: &Path<'a, R>) -> Key<'a, R> {
+        let (&node, prefix) = path.nodes
+            .split_last()
+            .expect("cannot build a cache key from an empty path");
+
+        let reachable = self.reachable(node);
+
+        let relevant = prefix
+            .iter()
+            .filter(|p| reachable.conflicts_with(&p.id) || reachable.excludes(p))
+            .cloned()
+            .collect();
+
+        (node, relevant)
+    }
+
+    /// Ids of all nodes the subtree of `node` may visit, including its own.
+    fn reachable(&mut self, node: &'a Node<R>) -> &Reachable<R::Id> {
+        self.reachable
+            .entry(node)
+            .or_insert_with(|| {
+                let mut visited = HashSet::new();
+                let mut ids = HashSet::new();
+                let mut excluded = HashSet::new();
+                let mut pending = vec![node];
+
+                while let Some(n) = pending.pop() {
+                    if visited.insert(n) {
+                        ids.insert(n.id.clone());
+                        excluded.extend(n.exclusions());
+
+                        if let Some(ref dependency) = n.dependency {
+                            pending.extend(dependency.nodes());
+                        }
+                    }
+                }
+
+                let classes = ids
+                    .iter()
+                    .map(|id: &R::Id| id.conflict_class())
+                    .collect();
+
+                Reachable {
+                    ids,
+                    classes,
+                    excluded
+                }
+            })
+    }
+}
+
+impl<'a, R: 'a + Resolvable> Default for Cache<'a, R> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl Cache {
+#[cfg(test)]
+mod tests {
+    use context::Context;
+    use package::ident::SimpleUnique;
+    use super::*;
+
+    type N = Node<MockResolvable>;
+
+    #[derive(Clone, Debug, Eq, Hash, PartialEq)]
+    struct MockResolvable {
+        deps: Vec<N>
+    }
+
+    impl Resolvable for MockResolvable {
+        type Id = SimpleUnique;
+
+        fn resolve<'a>(&'a self, path: Path<'a, Self>, _context: &mut Context<'a, Self>) -> Resolved<'a, Self> {
+            Resolved::success(path)
+        }
+
+        fn alternatives(&self) -> Vec<Vec<&N>> {
+            vec![self.deps.iter().collect()]
+        }
+    }
+
+    fn leaf(id: &'static str) -> N {
+        Node {
+            id: SimpleUnique { id },
+            dependency: None
+        }
+    }
+
+    #[test]
+    fn rebases_onto_equivalent_prefix() {
+        let (a, b, c) = (leaf("a"), leaf("b"), leaf("c"));
+
+        let mut cache = Cache::new();
+
+        let path_ac = Path::new(vec![&a, &c]);
+        cache.insert(&path_ac, &Resolved::success(path_ac.clone()));
+
+        // `b` can't conflict with `c`, so it doesn't affect the key
+        let path_bc = Path::new(vec![&b, &c]);
+        assert!(cache.contains(&path_bc));
+        assert_eq!(
+            cache.get(&path_bc),
+            Some(Resolved::success(path_bc.clone())));
+        assert_eq!(cache.hits(), 1);
+    }
+
+    #[test]
+    fn keys_on_conflicting_prefix() {
+        let (a, b) = (leaf("a"), leaf("b"));
+
+        let c: N = Node {
+            id: SimpleUnique { id: "c" },
+            dependency: Some(MockResolvable { deps: vec![a.clone()] })
+        };
+
+        let mut cache = Cache::new();
+
+        let path_bc = Path::new(vec![&b, &c]);
+        cache.insert(&path_bc, &Resolved::success(path_bc.clone()));
+
+        // `c` reaches `a`, so a prefix containing `a` is a different subproblem
+        let path_ac = Path::new(vec![&a, &c]);
+        assert!(!cache.contains(&path_ac));
+        assert_eq!(cache.get(&path_ac), None);
+        assert_eq!(cache.hits(), 0);
+        assert_eq!(cache.len(), 1);
+    }
 }
@@ -0,0 +1,263 @@
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+
+use budget::Aborted;
+use budget::Budget;
+use budget::Reason;
+use budget::Stats;
+use cache::Cache;
+use node::Node;
+use node::resolvable::Resolvable;
+use observer::Event;
+use observer::Observer;
+use observer::Silent;
+use path::Path;
+
+/// State shared by all steps of a single resolution.
+pub struct Context<'a, R: 'a + Resolvable> {
+    pub cache: Cache<'a, R>,
+    /// Maximum number of paths to look for, all of them if `None`. With a
+    /// limit of 0, nothing is searched.
+    pub limit: Option<usize>,
+    /// Receives the events of the resolution.
+    pub observer: Box<dyn Observer<R::Id>>,
+    pub budget: Budget,
+    /// Whether a node already selected by the path leading to it counts as
+    /// satisfied rather than conflicting. The lazy search has no context,
+    /// see `Solutions::allowing_cycles`.
+    pub allow_cycles: bool,
+    /// Cycles closed so far, see `cycles`
+    cycles: Vec<Vec<R::Id>>,
+    /// Number of nodes currently being solved.
+    pub(crate) depth: usize,
+    /// Paths wanted from the node being solved, see `wanted`
+    pub(crate) wanted: Option<usize>,
+    /// Nodes being solved, after the path given to the outermost one, see
+    /// `ancestors`
+    pub(crate) entered: Vec<&'a Node<R>>,
+    steps: usize,
+    /// Steps taken by the whole resolution, shared with its forks once it
+    /// has any
+    shared_steps: Option<Arc<AtomicUsize>>,
+    conflicts: usize,
+    /// Cache hits of joined contexts
+    joined_hits: usize,
+    started: Option<Instant>,
+    aborted: Option<Reason>
+}
+
+impl<'a, R: 'a + Resolvable> Context<'a, R> {
+    pub fn new() -> Self {
+        Context {
+            cache: Cache::new(),
+            limit: None,
+            observer: Box::new(Silent),
+            budget: Budget::new(),
+            allow_cycles: false,
+            cycles: vec![],
+            depth: 0,
+            wanted: None,
+            entered: vec![],
+            steps: 0,
+            shared_steps: None,
+            conflicts: 0,
+            joined_hits: 0,
+            started: None,
+            aborted: None
+        }
+    }
+
+    /// Context that stops at the first path found.
+    pub fn first_solution() -> Self {
+        Self::new().max_solutions(1)
+    }
+
+    /// Stops the search as soon as `limit` paths are found.
+    pub fn max_solutions(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Reports the events of the resolution to `observer`.
+    pub fn observed<O: 'static + Observer<R::Id>>(mut self, observer: O) -> Self {
+        self.observer = Box::new(observer);
+        self
+    }
+
+    /// Stops the resolution once `budget` is exceeded.
+    pub fn bounded(mut self, budget: Budget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Number of paths needed from the node whose dependency is being
+    /// resolved, all of them if `None`.
+    ///
+    /// Resolvables may stop looking once they have that many, and pass it on
+    /// with `Node::solve_wanting` to the nodes whose paths they return
+    /// unchanged, see `Resolved::merge_wanted`. Paths they filter or combine
+    /// with others should be solved in full with `Node::solve_in`.
+    pub fn wanted(&self) -> Option<usize> {
+        self.wanted
+    }
+
+    /// Accepts dependency cycles, see `allow_cycles`.
+    pub fn allowing_cycles(mut self) -> Self {
+        self.allow_cycles = true;
+        self
+    }
+
+    /// Distinct cycles closed so far, each from the node re-entered back to
+    /// itself, in the order they were found. Some may only belong to paths
+    /// that were rejected later on, see `Path::cycles` for those of a path.
+    pub fn cycles(&self) -> &[Vec<R::Id>] {
+        &self.cycles
+    }
+
+    pub(crate) fn close_cycle(&mut self, cycle: Vec<R::Id>) {
+        if !self.cycles.contains(&cycle) {
+            self.cycles.push(cycle);
+        }
+    }
+
+    /// Work done so far.
+    pub fn stats(&self) -> Stats {
+        Stats {
+            steps: self.steps,
+            conflicts: self.conflicts,
+            cache_hits: self.cache.hits() + self.joined_hits,
+            elapsed: self.started.map_or(Default::default(), |started| started.elapsed())
+        }
+    }
+
+    /// Set once the budget is exceeded. Results found afterwards are partial.
+    pub fn aborted(&self) -> Option<Aborted> {
+        self.aborted.map(|reason| Aborted {
+            reason,
+            stats: self.stats()
+        })
+    }
+
+    /// Context solving part of this resolution separately, sharing its
+    /// budget: the steps of every fork count against all of them. Its work
+    /// is added back by `join`.
+    pub(crate) fn fork<O: 'static + Observer<R::Id>>(&mut self, observer: O) -> Self {
+        self.started.get_or_insert_with(Instant::now);
+
+        let steps = self.steps;
+        let shared_steps = self.shared_steps
+            .get_or_insert_with(|| Arc::new(AtomicUsize::new(steps)))
+            .clone();
+
+        let mut forked = Context::new()
+            .bounded(self.budget.clone())
+            .observed(observer);
+        forked.allow_cycles = self.allow_cycles;
+        forked.shared_steps = Some(shared_steps);
+        forked
+    }
+
+    /// Nodes leading to the node at the end of `path`. Nodes sharing their
+    /// dependencies leave the paths of their siblings in it, so only the
+    /// path of the outermost node is taken as it is.
+    pub(crate) fn ancestors<'b>(&'b self, path: &'b Path<'a, R>) -> &'b [&'a Node<R>] {
+        match self.entered.is_empty() {
+            true => &path.nodes,
+            false => &self.entered
+        }
+    }
+
+    /// Marks the last node of `path` as being solved until `leave` is given
+    /// what this returns.
+    pub(crate) fn enter(&mut self, path: &Path<'a, R>) -> usize {
+        let outer = self.entered.len();
+        match outer {
+            0 => self.entered = path.nodes.clone(),
+            _ => self.entered.extend(path.nodes.last())
+        }
+        outer
+    }
+
+    pub(crate) fn leave(&mut self, outer: usize) {
+        self.entered.truncate(outer);
+    }
+
+    pub(crate) fn join(&mut self, forked: Context<'a, R>) {
+        self.steps += forked.steps;
+        self.conflicts += forked.conflicts;
+        self.joined_hits += forked.cache.hits() + forked.joined_hits;
+        self.aborted = self.aborted.or(forked.aborted);
+
+        for cycle in forked.cycles {
+            self.close_cycle(cycle);
+        }
+    }
+
+    /// Counts a step, returns whether the budget allows it.
+    pub(crate) fn proceed(&mut self) -> bool {
+        if self.aborted.is_some() {
+            return false;
+        }
+
+        self.started.get_or_insert_with(Instant::now);
+        self.aborted = match self.shared_steps {
+            Some(ref taken) => take_step(taken, &self.budget),
+            None => self.budget.exceeded(self.steps + 1)
+        };
+
+        if self.aborted.is_none() {
+            self.steps += 1;
+        }
+        self.aborted.is_none()
+    }
+
+    pub fn is_aborted(&self) -> bool {
+        self.aborted.is_some()
+    }
+
+    pub(crate) fn notify(&mut self, event: Event<R::Id>) {
+        if let Event::Conflict(_) = event {
+            self.conflicts += 1;
+        }
+        self.observer.notify(&event);
+    }
+}
+
+/// Counts a step in `taken`, shared between contexts, unless `budget`
+/// doesn't allow it.
+fn take_step(taken: &AtomicUsize, budget: &Budget) -> Option<Reason> {
+    let mut current = taken.load(Ordering::SeqCst);
+
+    loop {
+        if let Some(reason) = budget.exceeded(current + 1) {
+            return Some(reason);
+        }
+
+        match taken.compare_exchange(current, current + 1, Ordering::SeqCst, Ordering::SeqCst) {
+            Ok(_) => return None,
+            Err(actual) => current = actual
+        }
+    }
+}
+
+impl<'a, R: 'a + Resolvable> Default for Context<'a, R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, R: 'a + Resolvable> fmt::Debug for Context<'a, R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Context")
+            .field("cache", &self.cache)
+            .field("limit", &self.limit)
+            .field("budget", &self.budget)
+            .field("allow_cycles", &self.allow_cycles)
+            .field("depth", &self.depth)
+            .field("aborted", &self.aborted)
+            .finish()
+    }
+}
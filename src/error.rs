@@ -0,0 +1,105 @@
+use std::error;
+use std::fmt;
+use std::fmt::Debug;
+
+use budget::Aborted;
+use package::graph::NodeIndex;
+use package::version::ParseError;
+
+/// What can go wrong in the crate, for the APIs that don't panic. Ids are
+/// kept as they are, and displayed in their `Debug` form.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Error<Id = String> {
+    /// Package missing from the repository
+    UnknownPackage(Id),
+    /// Hard dependency of a package matching nothing in the repository
+    DanglingDependency { package: Id, dependency: String },
+    /// Root without any solution
+    NoSolution(Id),
+    /// Root without any solution, whose failure left no proof to explain
+    Unexplained(Id),
+    /// Version, requirement or marker that can't be parsed
+    Malformed(ParseError),
+    /// Resolution that ran out of budget before it could conclude
+    BudgetExceeded(Aborted),
+    /// Path cut by a path it doesn't start with
+    NotAPrefix,
+    /// Index of a node that isn't in the graph
+    MissingNode(NodeIndex)
+}
+
+impl<Id: Clone> Error<Id> {
+    pub fn unknown_package(id: &Id) -> Self {
+        Error::UnknownPackage(id.clone())
+    }
+
+    pub fn dangling_dependency<D: Debug>(package: &Id, dependency: &D) -> Self {
+        Error::DanglingDependency {
+            package: package.clone(),
+            dependency: format!("{:?}", dependency)
+        }
+    }
+
+    pub fn no_solution(id: &Id) -> Self {
+        Error::NoSolution(id.clone())
+    }
+
+    pub fn unexplained(id: &Id) -> Self {
+        Error::Unexplained(id.clone())
+    }
+}
+
+impl<Id: Debug> fmt::Display for Error<Id> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::UnknownPackage(id) => write!(f, "{:?} is not in the repository", id),
+            Error::DanglingDependency { package, dependency } => {
+                write!(f, "{:?} depends on {}, which matches nothing", package, dependency)
+            },
+            Error::NoSolution(id) => write!(f, "{:?} has no solution", id),
+            Error::Unexplained(id) => write!(f, "{:?} has no solution, but no proof of it was kept", id),
+            Error::Malformed(error) => write!(f, "{}", error),
+            Error::BudgetExceeded(aborted) => {
+                write!(f, "resolution aborted after {} steps: {:?}", aborted.stats.steps, aborted.reason)
+            },
+            Error::NotAPrefix => write!(f, "path doesn't start with the given prefix"),
+            Error::MissingNode(index) => write!(f, "{:?} is not in the graph", index)
+        }
+    }
+}
+
+impl<Id: Debug> error::Error for Error<Id> {}
+
+impl<Id> From<ParseError> for Error<Id> {
+    fn from(error: ParseError) -> Self {
+        Error::Malformed(error)
+    }
+}
+
+impl<Id> From<Aborted> for Error<Id> {
+    fn from(aborted: Aborted) -> Self {
+        Error::BudgetExceeded(aborted)
+    }
+}
+
+pub type Result<T, Id = String> = ::std::result::Result<T, Error<Id>>;
+
+#[cfg(test)]
+mod tests {
+    use budget::Reason;
+    use budget::Stats;
+    use package::version::Version;
+    use super::*;
+
+    #[test]
+    fn converts_and_displays() {
+        let malformed: Error = Version::parse("1.2").unwrap_err().into();
+        assert_eq!(malformed.to_string(), "expected major.minor.patch: \"1.2\"");
+
+        let aborted: Error = Aborted { reason: Reason::Steps, stats: Stats { steps: 10, ..Stats::default() } }.into();
+        assert_eq!(aborted.to_string(), "resolution aborted after 10 steps: Steps");
+
+        assert_eq!(Error::unknown_package(&"a").to_string(), "\"a\" is not in the repository");
+        assert_eq!(Error::no_solution(&"a"), Error::NoSolution("a"));
+    }
+}
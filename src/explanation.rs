@@ -0,0 +1,176 @@
+use std::fmt;
+
+/// Reason taking part in a failed resolution.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Incompatibility<Id> {
+    /// Resolution was requested for the package
+    Root(Id),
+    /// The package needs one of the ids
+    Dependency(Id, Vec<Id>),
+    /// The package needs all the ids of one of the sets
+    Alternatives(Id, Vec<Vec<Id>>),
+    /// The package needs a range or a virtual package, as the string reads,
+    /// which no package matches
+    Unmatched(Id, String),
+    /// No package has the id
+    Missing(Id),
+    /// The packages can't be selected together
+    Conflict(Id, Id),
+    /// The package can't be selected more than once
+    Repeated(Id),
+    /// The incompatibility holds among the packages selected for the host
+    OnHost(Box<Incompatibility<Id>>),
+    /// The first package breaks the second, so they can't be selected
+    /// together
+    Breaks(Id, Id),
+    /// Resolution was requested with the feature of the package turned on
+    Feature(Id, String),
+    /// The first package turns on the feature of the second one
+    Enables(Id, Id, String),
+    /// The package has no feature by that name
+    MissingFeature(Id, String),
+    /// If all `selected` packages are, one of `required` has to be selected
+    /// too. Follows from the incompatibilities at positions `because`.
+    /// With nothing selected nor required, there is no solution.
+    Derived {
+        selected: Vec<Id>,
+        required: Vec<Id>,
+        because: Vec<usize>
+    }
+}
+
+/// Derivation of a failure, in the style of PubGrub.
+///
+/// Every incompatibility is either a fact about the repository or follows
+/// from earlier ones. The last one is the failure itself.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Explanation<Id> {
+    pub incompatibilities: Vec<Incompatibility<Id>>
+}
+
+impl<Id> Explanation<Id> {
+    pub fn new(incompatibilities: Vec<Incompatibility<Id>>) -> Self {
+        Explanation { incompatibilities }
+    }
+}
+
+/// Numbered report, one incompatibility per line.
+impl<Id: fmt::Display> fmt::Display for Explanation<Id> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, incompatibility) in self.incompatibilities.iter().enumerate() {
+            writeln!(f, "{}. {}", i + 1, incompatibility)?;
+        }
+        Ok(())
+    }
+}
+
+impl<Id: fmt::Display> fmt::Display for Incompatibility<Id> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Incompatibility::Root(id) => write!(f, "{} is requested", id),
+            Incompatibility::Dependency(id, deps) if deps.is_empty() => {
+                write!(f, "{} has a dependency nothing satisfies", id)
+            },
+            Incompatibility::Dependency(id, deps) => {
+                write!(f, "{} depends on {}", id, join(deps, "or"))
+            },
+            Incompatibility::Alternatives(id, sets) => {
+                let sets: Vec<_> = sets.iter().map(|set| join(set, "and")).collect();
+                write!(f, "{} depends on {}", id, sets.join(", or "))
+            },
+            Incompatibility::Unmatched(id, target) => write!(f, "{} depends on {}, which nothing matches", id, target),
+            Incompatibility::Missing(id) => write!(f, "{} is not in the repository", id),
+            Incompatibility::Conflict(a, b) => write!(f, "{} conflicts with {}", a, b),
+            Incompatibility::Repeated(id) => write!(f, "{} can only be selected once", id),
+            Incompatibility::OnHost(incompatibility) => write!(f, "{} on the host", incompatibility),
+            Incompatibility::Breaks(a, b) => write!(f, "{} breaks {}", a, b),
+            Incompatibility::Feature(id, name) => write!(f, "{} is requested with feature {}", id, name),
+            Incompatibility::Enables(a, b, name) => write!(f, "{} turns on feature {} of {}", a, name, b),
+            Incompatibility::MissingFeature(id, name) => write!(f, "{} has no feature {}", id, name),
+            Incompatibility::Derived { selected, required, because } => {
+                let lines: Vec<_> = because.iter().map(|i| i + 1).collect();
+                write!(f, "Because of {}, ", join(&lines, "and"))?;
+
+                match (selected.len(), required.len()) {
+                    (0, 0) => write!(f, "there is no solution"),
+                    (0, _) => write!(f, "{} is required", join(required, "or")),
+                    (1, 0) => write!(f, "{} can't be selected", selected[0]),
+                    (2, 0) => write!(f, "{} can't both be selected", join(selected, "and")),
+                    (_, 0) => write!(f, "{} can't all be selected", join(selected, "and")),
+                    (1, _) => write!(f, "{} requires {}", selected[0], join(required, "or")),
+                    (_, _) => write!(f, "{} require {}", join(selected, "and"), join(required, "or"))
+                }
+            }
+        }
+    }
+}
+
+/// "a", "a or b", "a, b or c"
+fn join<T: fmt::Display>(items: &[T], conjunction: &str) -> String {
+    let items: Vec<_> = items.iter().map(|item| item.to_string()).collect();
+
+    match items.split_last() {
+        Some((last, init)) if !init.is_empty() => {
+            format!("{} {} {}", init.join(", "), conjunction, last)
+        },
+        Some((last, _)) => last.clone(),
+        None => String::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn joins() {
+        assert_eq!(join::<u8>(&[], "or"), "");
+        assert_eq!(join(&[1], "or"), "1");
+        assert_eq!(join(&[1, 2], "or"), "1 or 2");
+        assert_eq!(join(&[1, 2, 3], "and"), "1, 2 and 3");
+    }
+
+    #[test]
+    fn renders_report() {
+        let explanation = Explanation::new(vec![
+            Incompatibility::Root("a"),
+            Incompatibility::Dependency("a", vec!["b", "c"]),
+            Incompatibility::Missing("c"),
+            Incompatibility::Conflict("b", "a"),
+            Incompatibility::Derived {
+                selected: vec!["a"],
+                required: vec!["b"],
+                because: vec![1, 2]
+            },
+            Incompatibility::Derived {
+                selected: vec![],
+                required: vec![],
+                because: vec![0, 3, 4]
+            }]);
+
+        assert_eq!(
+            explanation.to_string(),
+            "1. a is requested\n\
+             2. a depends on b or c\n\
+             3. c is not in the repository\n\
+             4. b conflicts with a\n\
+             5. Because of 2 and 3, a requires b\n\
+             6. Because of 1, 4 and 5, there is no solution\n");
+    }
+
+    #[test]
+    fn renders_alternatives() {
+        let explanation = Explanation::new(vec![
+            Incompatibility::Alternatives("a", vec![vec!["b", "c"], vec!["d"]]),
+            Incompatibility::Repeated("a"),
+            Incompatibility::Unmatched("a", "e >=2".to_string()),
+            Incompatibility::OnHost(Box::new(Incompatibility::Conflict("b", "c")))]);
+
+        assert_eq!(
+            explanation.to_string(),
+            "1. a depends on b and c, or d\n\
+             2. a can only be selected once\n\
+             3. a depends on e >=2, which nothing matches\n\
+             4. b conflicts with c on the host\n");
+    }
+}
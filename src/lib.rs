@@ -1,9 +1,20 @@
-mod cache;
+pub mod budget;
+pub mod cache;
+pub mod context;
+pub mod error;
+pub mod explanation;
 pub mod node;
+pub mod observer;
 pub mod package;
 pub mod path;
+pub mod ranking;
+pub mod resolvables;
+pub mod sat;
 pub mod utils;
 
+pub use error::Error;
+pub use error::Result;
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -23,6 +23,7 @@ impl<'a, T: 'a + Resolvable> Cause<'a, T> {
         Self::new(nodes)
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn add(mut self, node: &'a Node<T>) -> Self {
         if !self.has(node) {
             self.nodes.insert(node);
@@ -49,6 +50,7 @@ impl<'a, T: 'a + Resolvable> Cause<'a, T> {
 
 #[cfg(test)]
 mod tests {
+    use context::Context;
     use node::resolved::Resolved;
     use path::Path;
     use super::*;
@@ -62,9 +64,13 @@ mod tests {
     impl Resolvable for MockResolvable {
         type Id = SimpleUnique;
 
-        fn resolve<'a>(&'a self, path: Path<'a, Self>) -> Resolved<'a, Self> {
+        fn resolve<'a>(&'a self, path: Path<'a, Self>, _context: &mut Context<'a, Self>) -> Resolved<'a, Self> {
             Resolved::success(path)
         }
+
+        fn alternatives(&self) -> Vec<Vec<&N>> {
+            vec![]
+        }
     }
 
     #[test]
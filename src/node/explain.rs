@@ -0,0 +1,172 @@
+use explanation::Explanation;
+use explanation::Incompatibility;
+use package::ident::Ident;
+use path::Path;
+use utils::Selections;
+use super::Node;
+use super::resolvable::Resolvable;
+use super::solvability::Solvability;
+
+/// Explains why `node` can't be solved on its own, `None` if it can.
+///
+/// Like `iterative::solve`, assumes the dependencies resolve their
+/// alternatives the way `Resolvable::alternatives` describes them. Cycles
+/// are explained as a package selected twice, whatever the context allows.
+pub fn explain<R: Resolvable>(node: &Node<R>) -> Option<Explanation<R::Id>> {
+    if node.solve(Path::new(vec![])).is_success() {
+        return None;
+    }
+
+    let mut report = Report { incompatibilities: vec![] };
+    let root = report.add(Incompatibility::Root(node.id.clone()));
+    let (refuted, _) = report.refute(node, &Path::new(vec![]));
+    report.add(Incompatibility::Derived { selected: vec![], required: vec![], because: vec![root, refuted] });
+
+    Some(Explanation::new(report.incompatibilities))
+}
+
+/// Incompatibilities stated so far, each one once.
+struct Report<Id> {
+    incompatibilities: Vec<Incompatibility<Id>>
+}
+
+impl<Id: Ident> Report<Id> {
+    /// Position of `incompatibility`, added if it isn't there yet.
+    fn add(&mut self, incompatibility: Incompatibility<Id>) -> usize {
+        match self.incompatibilities.iter().position(|known| *known == incompatibility) {
+            Some(position) => position,
+            None => {
+                self.incompatibilities.push(incompatibility);
+                self.incompatibilities.len() - 1
+            }
+        }
+    }
+
+    /// Derives that `node` can't be selected below `path`, which it must
+    /// fail under. Returns the derived incompatibility, along with the ids
+    /// of `path` it holds for.
+    fn refute<'a, R: Resolvable<Id = Id>>(&mut self, node: &'a Node<R>, path: &Path<'a, R>) -> (usize, Vec<Id>) {
+        let appended = path.clone().append(node);
+
+        // The path itself has no conflict, so it involves the node
+        if let Some((earlier, later)) = conflict(&appended.nodes) {
+            let fact = self.conflict(&appended.nodes, (earlier, later));
+            return (fact, vec![appended.nodes[earlier].id.clone()]);
+        }
+
+        let alternatives = match node.dependency {
+            Some(ref dependency) => dependency.alternatives(),
+            None => vec![]
+        };
+
+        let dependency = self.add(dependency_fact(node, &alternatives));
+        let mut because = vec![dependency];
+        let mut blamed = vec![];
+
+        for alternative in alternatives.iter() {
+            let (refuted, ids) = self.refute_alternative(alternative, &appended);
+            because.push(refuted);
+            blamed.extend(ids);
+        }
+
+        let mut selected = vec![node.id.clone()];
+        for id in blamed {
+            if !selected.contains(&id) {
+                selected.push(id);
+            }
+        }
+
+        let derived = self.add(Incompatibility::Derived { selected: selected.clone(), required: vec![], because });
+        selected.remove(0);
+        (derived, selected)
+    }
+
+    /// Derives that the nodes of `alternative` can't all be selected below
+    /// `path`, which they must fail under.
+    fn refute_alternative<'a, R: Resolvable<Id = Id>>(&mut self, alternative: &[&'a Node<R>], path: &Path<'a, R>) -> (usize, Vec<Id>) {
+        let mut solved = vec![];
+
+        for node in alternative {
+            let resolved = node.solve(path.clone());
+            if !resolved.is_success() {
+                return self.refute(node, path);
+            }
+            solved.push(resolved.paths);
+        }
+
+        // Each node can be selected on its own, but not along with the others
+        let mut because = vec![];
+        let mut blamed = vec![];
+
+        for selection in Selections::new(solved) {
+            let combined = Path::combine(path, selection);
+
+            if let Some((earlier, later)) = conflict(&combined.nodes) {
+                let fact = self.conflict(&combined.nodes, (earlier, later));
+                if !because.contains(&fact) {
+                    because.push(fact);
+                }
+                if earlier < path.nodes.len() {
+                    blamed.push(combined.nodes[earlier].id.clone());
+                }
+            }
+        }
+
+        let mut selected: Vec<_> = alternative.iter().map(|node| node.id.clone()).collect();
+        for id in blamed {
+            if !selected.contains(&id) {
+                selected.push(id);
+            }
+        }
+
+        let derived = self.add(Incompatibility::Derived { selected: selected.clone(), required: vec![], because });
+        let context = selected.split_off(alternative.len());
+        (derived, context)
+    }
+
+    /// States the conflict between the nodes at positions `earlier` and
+    /// `later` of `nodes`.
+    fn conflict<R: Resolvable<Id = Id>>(&mut self, nodes: &[&Node<R>], (earlier, later): (usize, usize)) -> usize {
+        let (a, b) = (&nodes[earlier].id, &nodes[later].id);
+
+        let fact = match a == b {
+            true => Incompatibility::Repeated(a.clone()),
+            false => match nodes[earlier].exclusions().contains(b) {
+                true => Incompatibility::Conflict(a.clone(), b.clone()),
+                false => Incompatibility::Conflict(b.clone(), a.clone())
+            }
+        };
+
+        self.add(fact)
+    }
+}
+
+/// What `node` depends on, as its alternatives read.
+fn dependency_fact<R: Resolvable>(node: &Node<R>, alternatives: &[Vec<&Node<R>>]) -> Incompatibility<R::Id> {
+    let ids: Vec<Vec<_>> = alternatives
+        .iter()
+        .map(|alternative| alternative.iter().map(|node| node.id.clone()).collect())
+        .collect();
+
+    match ids.iter().all(|alternative| alternative.len() == 1) {
+        true => Incompatibility::Dependency(node.id.clone(), ids.into_iter().flatten().collect()),
+        false => Incompatibility::Alternatives(node.id.clone(), ids)
+    }
+}
+
+/// Positions of the first two nodes of `nodes` that can't be selected
+/// together, the later one as early as possible.
+fn conflict<R: Resolvable>(nodes: &[&Node<R>]) -> Option<(usize, usize)> {
+    if !matches!(Node::solvability(&Path::new(nodes.to_vec())), Solvability::Conflict) {
+        return None;
+    }
+
+    (1..nodes.len())
+        .flat_map(|later| (0..later).map(move |earlier| (earlier, later)))
+        .find(|&(earlier, later)| {
+            let (a, b) = (nodes[earlier], nodes[later]);
+            R::Id::are_conflicting(&[a.id.clone(), b.id.clone()])
+                || a.exclusions().contains(&b.id)
+                || b.exclusions().contains(&a.id)
+        })
+}
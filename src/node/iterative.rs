@@ -0,0 +1,495 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::mem;
+use std::ptr;
+use std::vec;
+
+use context::Context;
+use observer::Event;
+use package::ident::Ident;
+use path::Path;
+use utils::Selections;
+use super::Node;
+use super::cause::Cause;
+use super::resolvable::Resolvable;
+use super::resolved::Resolved;
+use super::solvability::Solvability;
+
+/// Nodes below the path a node was solved under, starting with the node,
+/// and the cycles they close.
+#[derive(Clone)]
+struct Suffix<'a, R: 'a + Resolvable> {
+    /// Stored in reverse, so that parents are added in constant time
+    nodes: Vec<&'a Node<R>>,
+    cycles: Vec<Vec<R::Id>>
+}
+
+impl<'a, R: 'a + Resolvable> Suffix<'a, R> {
+    fn empty() -> Self {
+        Suffix { nodes: vec![], cycles: vec![] }
+    }
+
+    /// The suffix followed by `next`, found below it.
+    fn then(&self, next: Suffix<'a, R>) -> Self {
+        let mut nodes = next.nodes;
+        nodes.extend(self.nodes.iter().cloned());
+
+        let mut cycles = self.cycles.clone();
+        for cycle in next.cycles {
+            if !cycles.contains(&cycle) {
+                cycles.push(cycle);
+            }
+        }
+        Suffix { nodes, cycles }
+    }
+
+    /// `prefix` followed by the suffix.
+    fn below(self, prefix: &Path<'a, R>) -> Path<'a, R> {
+        let mut path = prefix.clone();
+        path.nodes.extend(self.nodes.into_iter().rev());
+
+        self.cycles
+            .into_iter()
+            .fold(path, |path, cycle| path.closing(cycle))
+    }
+
+    /// What `path` adds to `prefix`.
+    fn of(path: Path<'a, R>, prefix: &Path<'a, R>) -> Self {
+        let Path { mut nodes, cycles } = path.suffix(prefix);
+        nodes.reverse();
+        Suffix { nodes, cycles }
+    }
+}
+
+/// Result of solving a node, relative to the path it was solved under.
+struct Partial<'a, R: 'a + Resolvable> {
+    suffixes: Vec<Suffix<'a, R>>,
+    cause: Cause<'a, R>
+}
+
+impl<'a, R: 'a + Resolvable> Partial<'a, R> {
+    fn failure(cause: Cause<'a, R>) -> Self {
+        Partial { suffixes: vec![], cause }
+    }
+}
+
+/// Node being solved.
+struct Frame<'a, R: 'a + Resolvable> {
+    node: &'a Node<R>,
+    /// Alternatives left to try
+    alternatives: vec::IntoIter<Vec<&'a Node<R>>>,
+    /// Alternative being tried, if any is left
+    current: Option<Vec<&'a Node<R>>>,
+    /// Results of the nodes of the current alternative solved so far
+    solved: Vec<Partial<'a, R>>,
+    /// Paths of the current alternative left to extend, with the number of
+    /// its nodes they satisfy, when its nodes are solved in turn, see
+    /// `Node::solve_together`. The last one is on the trail while its next
+    /// node is solved.
+    pending: Vec<(usize, Suffix<'a, R>)>,
+    /// Paths of the current alternative found by solving its nodes in turn
+    found: Partial<'a, R>,
+    /// Results of the alternatives already tried
+    merged: Partial<'a, R>,
+    /// Number of paths needed from the node, all of them if `None`
+    wanted: Option<usize>
+}
+
+impl<'a, R: 'a + Resolvable> Frame<'a, R> {
+    fn new(node: &'a Node<R>, alternatives: Vec<Vec<&'a Node<R>>>, wanted: Option<usize>) -> Self {
+        let mut alternatives = alternatives.into_iter();
+
+        Frame {
+            node,
+            current: alternatives.next(),
+            alternatives,
+            solved: vec![],
+            pending: vec![(0, Suffix::empty())],
+            found: Partial::failure(Cause::empty()),
+            merged: Partial::failure(Cause::empty()),
+            wanted
+        }
+    }
+
+    /// Next node of the current alternative to solve, with the number of
+    /// paths needed from it. Only the paths of the last node are taken as
+    /// they are. When nodes are solved in turn, puts the path it is solved
+    /// below on the trail.
+    fn next_child(&mut self, trail: &mut Trail<'a, R>) -> Option<(&'a Node<R>, Option<usize>)> {
+        let alternative = match self.current {
+            Some(ref alternative) => alternative,
+            None => return None
+        };
+
+        if !R::shares_nodes() {
+            let wanted = match alternative.len() {
+                1 => self.wanted,
+                _ => None
+            };
+            return alternative.get(self.solved.len()).map(|&child| (child, wanted));
+        }
+
+        while let Some(&(count, _)) = self.pending.last() {
+            if count < alternative.len() {
+                break;
+            }
+            let (_, suffix) = self.pending.pop().unwrap();
+            self.found.suffixes.push(suffix);
+
+            if self.found.suffixes.len() >= self.wanted.unwrap_or(usize::MAX) {
+                self.pending.clear();
+            }
+        }
+
+        let &(count, ref solved) = self.pending.last()?;
+        for &node in solved.nodes.iter().rev() {
+            trail.push(node);
+        }
+
+        let wanted = match count + 1 == alternative.len() {
+            true => self.wanted.map(|wanted| wanted - self.found.suffixes.len()),
+            false => None
+        };
+        Some((alternative[count], wanted))
+    }
+
+    /// Takes the result of the last child, taking the path it was solved
+    /// below off the trail when nodes are solved in turn.
+    fn receive(&mut self, partial: Partial<'a, R>, trail: &mut Trail<'a, R>) {
+        if !R::shares_nodes() {
+            self.solved.push(partial);
+            return;
+        }
+
+        let (count, solved) = self.pending.pop().unwrap();
+        for _ in solved.nodes.iter() {
+            trail.pop();
+        }
+
+        let cause = mem::replace(&mut self.found.cause, Cause::empty());
+        self.found.cause = cause.merge(partial.cause);
+
+        // In reverse, so that the first path is extended first
+        for suffix in partial.suffixes.into_iter().rev() {
+            self.pending.push((count + 1, solved.then(suffix)));
+        }
+    }
+}
+
+/// Current path, with the conflict classes of its nodes if they all have one
+/// and the ids they declare they can't be selected with, so that conflicts
+/// are checked in constant time.
+struct Trail<'a, R: 'a + Resolvable> {
+    nodes: Vec<&'a Node<R>>,
+    /// Number of nodes of the path solved under
+    base: usize,
+    classes: Option<HashMap<R::Id, usize>>,
+    /// Number of classes with more than one node
+    duplicates: usize,
+    /// Number of nodes with each id
+    ids: HashMap<R::Id, usize>,
+    /// Number of nodes declaring each id they can't be selected with
+    excluded: HashMap<R::Id, usize>,
+    /// Number of excluded ids some node has
+    violated: usize
+}
+
+impl<'a, R: 'a + Resolvable> Trail<'a, R> {
+    fn new(path: &Path<'a, R>) -> Self {
+        let mut trail = Trail {
+            nodes: vec![],
+            base: path.nodes.len(),
+            classes: Some(HashMap::new()),
+            duplicates: 0,
+            ids: HashMap::new(),
+            excluded: HashMap::new(),
+            violated: 0
+        };
+
+        for node in path.nodes.iter() {
+            trail.push(node);
+        }
+        trail
+    }
+
+    fn push(&mut self, node: &'a Node<R>) {
+        self.nodes.push(node);
+
+        if increment(&mut self.ids, node.id.clone()) && self.excluded.contains_key(&node.id) {
+            self.violated += 1;
+        }
+        for id in Self::declared(node) {
+            if increment(&mut self.excluded, id.clone()) && self.ids.contains_key(&id) {
+                self.violated += 1;
+            }
+        }
+
+        match (self.classes.as_mut(), node.id.conflict_class()) {
+            (Some(classes), Some(class)) => {
+                let count = classes.entry(class).or_insert(0);
+                *count += 1;
+                if *count == 2 {
+                    self.duplicates += 1;
+                }
+            },
+            _ => self.classes = None
+        }
+    }
+
+    fn pop(&mut self) {
+        let node = self.nodes.pop().unwrap();
+
+        for id in Self::declared(node) {
+            if decrement(&mut self.excluded, &id) && self.ids.contains_key(&id) {
+                self.violated -= 1;
+            }
+        }
+        if decrement(&mut self.ids, &node.id) && self.excluded.contains_key(&node.id) {
+            self.violated -= 1;
+        }
+
+        if let Some(classes) = self.classes.as_mut() {
+            let class = node.id.conflict_class().unwrap();
+            let count = classes.get_mut(&class).unwrap();
+            if *count == 2 {
+                self.duplicates -= 1;
+            }
+            *count -= 1;
+        }
+    }
+
+    /// Ids `node` declares it can't be selected with, other than its own,
+    /// like `Path::excluding`.
+    fn declared(node: &Node<R>) -> Vec<R::Id> {
+        node.exclusions()
+            .into_iter()
+            .filter(|id| *id != node.id)
+            .collect()
+    }
+
+    /// Checks if `node` itself is on the trail, see `Path::has`.
+    fn has(&self, node: &Node<R>) -> bool {
+        self.ids.contains_key(&node.id) && self.nodes.iter().any(|&known| ptr::eq(known, node) || known == node)
+    }
+
+    fn conflict(&self) -> bool {
+        match self.classes {
+            Some(_) => self.duplicates > 0 || self.violated > 0,
+            None => Path::new(self.nodes.clone()).conflict()
+        }
+    }
+}
+
+/// Counts one more `key`, returns whether it wasn't counted before.
+fn increment<K: Eq + Hash>(counts: &mut HashMap<K, usize>, key: K) -> bool {
+    let count = counts.entry(key).or_insert(0);
+    *count += 1;
+    *count == 1
+}
+
+/// Counts one less `key`, returns whether it is no longer counted.
+fn decrement<K: Eq + Hash>(counts: &mut HashMap<K, usize>, key: &K) -> bool {
+    let count = counts.get_mut(key).unwrap();
+    *count -= 1;
+
+    if *count == 0 {
+        counts.remove(key);
+        return true;
+    }
+    false
+}
+
+/// Solves a node with an explicit stack, so that the depth of the tree is
+/// only limited by memory.
+///
+/// Gives the same result as `Node::solve_in`, for dependencies resolving
+/// their alternatives the way `Resolvable::alternatives` describes them:
+/// the paths of all alternatives, in order, each combining one path per
+/// node of the alternative, or solving them in turn if the resolvable
+/// shares nodes. Subproblems are not cached.
+///
+/// Like `Node::solve_in`, stops trying alternatives once the limit of the
+/// context is reached.
+pub fn solve<'a, R: Resolvable>(node: &'a Node<R>, path: Path<'a, R>, context: &mut Context<'a, R>) -> Resolved<'a, R> {
+    let wanted = match context.depth {
+        0 => context.limit,
+        _ => None
+    };
+    if wanted == Some(0) {
+        return Resolved::failure(Cause::empty());
+    }
+
+    let mut trail = Trail::new(&path);
+    let mut frames = vec![];
+    let mut returned = enter(node, wanted, &mut trail, &mut frames, context);
+
+    loop {
+        if let Some(partial) = returned.take() {
+            match frames.last_mut() {
+                Some(frame) => frame.receive(partial, &mut trail),
+                None => return finish(path, partial)
+            }
+        }
+
+        let next = frames.last_mut().unwrap().next_child(&mut trail);
+        returned = match next {
+            Some((child, wanted)) => enter(child, wanted, &mut trail, &mut frames, context),
+            None => advance(&mut trail, &mut frames, context)
+        };
+    }
+}
+
+/// Starts solving `node`. Returns its result unless it has to descend.
+fn enter<'a, R: Resolvable>(node: &'a Node<R>, wanted: Option<usize>, trail: &mut Trail<'a, R>, frames: &mut Vec<Frame<'a, R>>, context: &mut Context<'a, R>) -> Option<Partial<'a, R>> {
+    if !context.proceed() {
+        return Some(Partial::failure(Cause::empty()));
+    }
+
+    // A shared node already selected satisfies itself
+    if R::shares_nodes() && trail.has(node) {
+        return Some(Partial {
+            suffixes: vec![Suffix::empty()],
+            cause: Cause::empty()
+        });
+    }
+
+    if context.allow_cycles {
+        // The trail also holds the paths of siblings solved before when
+        // nodes are solved in turn, only frames lead to the node
+        let ancestors = || trail.nodes[..trail.base].iter().chain(frames.iter().map(|frame| &frame.node));
+
+        if let Some(start) = ancestors().position(|known| known.id == node.id) {
+            let mut cycle: Vec<_> = ancestors().skip(start).map(|known| known.id.clone()).collect();
+            cycle.push(node.id.clone());
+
+            context.notify(Event::Cycle(node.id.clone()));
+            context.close_cycle(cycle.clone());
+            return Some(Partial {
+                suffixes: vec![Suffix { nodes: vec![], cycles: vec![cycle] }],
+                cause: Cause::empty()
+            });
+        }
+    }
+
+    trail.push(node);
+    context.notify(Event::Entered(node.id.clone()));
+
+    if trail.conflict() {
+        context.notify(Event::Conflict(node.id.clone()));
+
+        let cause = Node::conflict_cause(&Path::new(trail.nodes.clone()));
+        trail.pop();
+
+        return Some(Partial::failure(cause));
+    }
+
+    match node.dependency {
+        Some(ref dependency) => {
+            frames.push(Frame::new(node, dependency.alternatives(), wanted));
+            None
+        },
+        None => {
+            trail.pop();
+            context.notify(Event::Solved(node.id.clone(), 1));
+            Some(Partial {
+                suffixes: vec![Suffix { nodes: vec![node], cycles: vec![] }],
+                cause: Cause::empty()
+            })
+        }
+    }
+}
+
+/// Combines the results of the current alternative of the top frame.
+/// Returns the result of its node once all alternatives are done.
+fn advance<'a, R: Resolvable>(trail: &mut Trail<'a, R>, frames: &mut Vec<Frame<'a, R>>, context: &mut Context<'a, R>) -> Option<Partial<'a, R>> {
+    {
+        let frame = frames.last_mut().unwrap();
+
+        if frame.current.take().is_some() {
+            let combined = match R::shares_nodes() {
+                true => mem::replace(&mut frame.found, Partial::failure(Cause::empty())),
+                false => combine(&trail.nodes, frame.solved.drain(..).collect(), frame.wanted)
+            };
+
+            let cause = mem::replace(&mut frame.merged.cause, Cause::empty());
+            frame.merged.cause = cause.merge(combined.cause);
+            frame.merged.suffixes.extend(combined.suffixes);
+
+            let enough = frame.merged.suffixes.len() >= frame.wanted.unwrap_or(usize::MAX);
+            frame.current = match enough {
+                true => None,
+                false => frame.alternatives.next()
+            };
+            if frame.current.is_some() {
+                frame.pending.push((0, Suffix::empty()));
+                return None;
+            }
+        }
+    }
+
+    let Frame { node, merged, wanted, .. } = frames.pop().unwrap();
+    trail.pop();
+
+    let mut suffixes = merged.suffixes;
+    if let Some(wanted) = wanted {
+        suffixes.truncate(wanted);
+    }
+    for suffix in suffixes.iter_mut() {
+        suffix.nodes.push(node);
+    }
+
+    match suffixes.len() {
+        0 => context.notify(Event::Exhausted(node.id.clone())),
+        n => context.notify(Event::Solved(node.id.clone(), n))
+    }
+
+    Some(Partial {
+        suffixes,
+        cause: merged.cause.above(node)
+    })
+}
+
+/// Paths satisfying all nodes of an alternative together, below `trail`,
+/// up to `wanted` of them.
+fn combine<'a, R: Resolvable>(trail: &[&'a Node<R>], mut solved: Vec<Partial<'a, R>>, wanted: Option<usize>) -> Partial<'a, R> {
+    let cause = solved
+        .iter()
+        .fold(Cause::empty(), |acc, partial| acc.merge(partial.cause.clone()));
+
+    if solved.iter().any(|partial| partial.suffixes.is_empty()) {
+        return Partial::failure(cause);
+    }
+
+    // Paths of a single node are already known not to conflict
+    if solved.len() == 1 {
+        return Partial {
+            suffixes: solved.pop().unwrap().suffixes,
+            cause
+        };
+    }
+
+    let prefix = Path::new(trail.to_vec());
+    let suffixes = Selections::new(solved.into_iter().map(|partial| partial.suffixes).collect())
+        .map(|selection| {
+            let paths = selection
+                .into_iter()
+                .map(|suffix| suffix.below(&prefix))
+                .collect();
+            Path::combine(&prefix, paths)
+        })
+        .filter(|path| matches!(Node::solvability(path), Solvability::Ok))
+        .take(wanted.unwrap_or(usize::MAX))
+        .map(|path| Suffix::of(path, &prefix))
+        .collect();
+
+    Partial { suffixes, cause }
+}
+
+fn finish<'a, R: Resolvable>(prefix: Path<'a, R>, partial: Partial<'a, R>) -> Resolved<'a, R> {
+    let paths = partial.suffixes
+        .into_iter()
+        .map(|suffix| suffix.below(&prefix))
+        .collect();
+
+    Resolved::new(paths, partial.cause)
+}
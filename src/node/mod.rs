@@ -1,13 +1,29 @@
 pub mod cause;
+pub mod explain;
+pub mod iterative;
+pub mod outcome;
+pub mod parallel;
 pub mod resolvable;
 pub mod resolved;
+pub mod solutions;
 pub mod solvability;
 
+use std::mem;
+
+use context::Context;
+use explanation::Explanation;
+use observer::Event;
 use path::Path;
+use ranking::Best;
+use ranking::Objective;
 use self::cause::Cause;
+use self::outcome::Outcome;
 use self::resolved::Resolved;
 use self::resolvable::Resolvable;
+use self::solutions::Progress;
+use self::solutions::Solutions;
 use self::solvability::Solvability;
+use utils::Selections;
 
 /// Node of a DFS-traversable tree.
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
@@ -18,31 +34,286 @@ pub struct Node<R: Resolvable> {
 
 impl <R: Resolvable> Node<R> {
     pub fn solve<'a>(&'a self, path: Path<'a, R>) -> Resolved<'a, R> {
-        let path = path.append(self);
+        self.solve_in(path, &mut Context::new())
+    }
+
+    /// Explains why the node can't be solved, `None` if it can. See
+    /// `explain::explain`.
+    pub fn explain(&self) -> Option<Explanation<R::Id>> {
+        explain::explain(self)
+    }
+
+    /// Lazily enumerates the paths solving the node.
+    pub fn solutions<'a>(&'a self, path: Path<'a, R>) -> Solutions<'a, R> {
+        Solutions::new(self, path)
+    }
+
+    /// Solves the node within the budget of `context`.
+    pub fn solve_within<'a>(&'a self, path: Path<'a, R>, context: &mut Context<'a, R>) -> Outcome<'a, R> {
+        let resolved = self.solve_in(path, context);
+
+        match context.aborted() {
+            Some(aborted) => Outcome::Aborted(aborted),
+            None => Outcome::Finished(resolved)
+        }
+    }
+
+    /// Solves the node, sharing `context` with all nested subproblems.
+    ///
+    /// If the context allows cycles, a node already in the path is
+    /// satisfied by it and left out, the cycle being kept in the paths
+    /// selecting it, see `Path::cycles`.
+    ///
+    /// Once the budget of the context is exceeded, nodes are no longer
+    /// explored and the result is partial, see `Context::aborted`.
+    ///
+    /// If the context has a limit, only the outermost call is limited, as
+    /// nested results may still be rejected when combined with their
+    /// siblings. Resolvables stop looking once they have found enough paths
+    /// where they can tell, see `Context::wanted`.
+    pub fn solve_in<'a>(&'a self, path: Path<'a, R>, context: &mut Context<'a, R>) -> Resolved<'a, R> {
+        let wanted = match context.depth {
+            0 => context.limit,
+            _ => None
+        };
+
+        self.solve_wanting(path, wanted, context)
+    }
+
+    /// Like `solve_in`, but only looks for up to `wanted` paths, all of them
+    /// if `None`. For resolvables passing on the paths of a node unchanged.
+    pub fn solve_wanting<'a>(&'a self, path: Path<'a, R>, wanted: Option<usize>, context: &mut Context<'a, R>) -> Resolved<'a, R> {
+        if wanted == Some(0) {
+            return Resolved::failure(Cause::empty());
+        }
+
+        let outer = mem::replace(&mut context.wanted, wanted);
+        context.depth += 1;
+        let Resolved { paths, cause } = self.solve_step(path, context);
+        context.depth -= 1;
+        context.wanted = outer;
+
+        match wanted {
+            Some(wanted) => Resolved::new(paths.into_iter().take(wanted).collect(), cause),
+            None => Resolved::new(paths, cause)
+        }
+    }
+
+    /// Paths satisfying all of `nodes` together below `path`, up to `wanted`
+    /// of them, all of them if `None`. Each node is solved on its own, and
+    /// their paths are combined with `Path::combine`.
+    ///
+    /// If the resolvable shares nodes, each node is solved below each path
+    /// of the ones before it instead, so that a node they share makes the
+    /// same choice for all of them, like in `Solutions`.
+    pub fn solve_together<'a>(nodes: &[&'a Self], path: Path<'a, R>, wanted: Option<usize>, context: &mut Context<'a, R>) -> Resolved<'a, R> {
+        // The paths of a single node are taken as they are
+        if let [node] = nodes {
+            return node.solve_wanting(path, wanted, context);
+        }
+
+        if R::shares_nodes() {
+            let mut found = vec![];
+            let mut cause = Cause::empty();
+            Self::solve_in_turn(nodes, path, wanted, context, &mut found, &mut cause);
+
+            return Resolved::new(found, cause);
+        }
+
+        let results: Vec<_> = nodes
+            .iter()
+            .map(|node| node.solve_in(path.clone(), context))
+            .collect();
+
+        let cause = results
+            .iter()
+            .fold(Cause::empty(), |acc, res| acc.merge(res.cause.clone()));
+
+        // If any node failed, they can't be solved together
+        if !results.iter().all(|res| res.is_success()) {
+            return Resolved::failure(cause);
+        }
 
-        let solvability = Self::solvability(&path);
-        println!("Node:solve {:?} ", self.id);
+        let paths = Selections::new(results.into_iter().map(|res| res.paths).collect())
+            .map(|paths| Path::combine(&path, paths))
+            .filter(|path| matches!(Self::solvability(path), Solvability::Ok))
+            .take(wanted.unwrap_or(usize::MAX))
+            .collect();
 
-        match solvability {
+        Resolved::new(paths, cause)
+    }
+
+    /// Adds to `found` the paths satisfying all of `nodes` below `path`, each
+    /// node being solved below each path of the ones before it, until there
+    /// are `wanted` of them if any.
+    fn solve_in_turn<'a>(nodes: &[&'a Self], path: Path<'a, R>, wanted: Option<usize>, context: &mut Context<'a, R>, found: &mut Vec<Path<'a, R>>, cause: &mut Cause<'a, R>) {
+        let (node, rest) = match nodes.split_first() {
+            Some(split) => split,
+            None => {
+                found.push(path);
+                return;
+            }
+        };
+
+        let resolved = match rest.is_empty() {
+            true => node.solve_wanting(path, wanted.map(|wanted| wanted - found.len()), context),
+            false => node.solve_in(path, context)
+        };
+        *cause = mem::replace(cause, Cause::empty()).merge(resolved.cause);
+
+        for path in resolved.paths {
+            if found.len() >= wanted.unwrap_or(usize::MAX) {
+                return;
+            }
+            Self::solve_in_turn(rest, path, wanted, context, found, cause);
+        }
+    }
+
+    /// Best path solving the node according to `objective`, as the only
+    /// path of the resolution.
+    ///
+    /// Paths are looked at one at a time, only the best one so far is kept.
+    /// Every path has to be looked at to know it's the best, so if the
+    /// budget of `context` is exceeded first, the search is aborted.
+    pub fn optimal<'a, O: Objective<R::Id>>(&'a self, path: Path<'a, R>, objective: O, context: &mut Context<'a, R>) -> Outcome<'a, R> {
+        let mut solutions = match context.allow_cycles {
+            true => self.solutions(path).allowing_cycles(),
+            false => self.solutions(path)
+        };
+        let mut best = Best::new(objective);
+
+        while context.proceed() {
+            match solutions.advance() {
+                Progress::Found(path) => best.offer(path),
+                Progress::Searching => {},
+                Progress::Done => return Outcome::Finished(Resolved::new(best.into_path().into_iter().collect(), Cause::empty()))
+            }
+        }
+
+        match context.aborted() {
+            Some(aborted) => Outcome::Aborted(aborted),
+            None => unreachable!("the search only stops early when aborted")
+        }
+    }
+
+    /// Like `solve_in`, but without recursion, so that arbitrarily deep
+    /// trees can be solved. See `iterative::solve` for the dependencies it
+    /// supports.
+    pub fn solve_iteratively<'a>(&'a self, path: Path<'a, R>, context: &mut Context<'a, R>) -> Resolved<'a, R> {
+        iterative::solve(self, path, context)
+    }
+
+    /// Like `solve_in`, but spreads the alternatives of the node over up to
+    /// `workers` threads. See `parallel::solve` for the dependencies it
+    /// supports.
+    pub fn solve_parallel<'a>(&'a self, path: Path<'a, R>, context: &mut Context<'a, R>, workers: usize) -> Resolved<'a, R>
+        where R: Send + Sync,
+              R::Id: 'static + Send + Sync
+    {
+        parallel::solve(self, path, context, workers)
+    }
+
+    fn solve_step<'a>(&'a self, path: Path<'a, R>, context: &mut Context<'a, R>) -> Resolved<'a, R> {
+        if !context.proceed() {
+            return Resolved::failure(Cause::empty());
+        }
+
+        // A shared node already selected satisfies itself
+        if R::shares_nodes() && path.has(self) {
+            return Resolved::success(path);
+        }
+
+        if context.allow_cycles {
+            let cycle = {
+                let ancestors = context.ancestors(&path);
+                ancestors
+                    .iter()
+                    .position(|node| node.id == self.id)
+                    .map(|start| ancestors[start..].iter().map(|node| node.id.clone()).collect::<Vec<_>>())
+            };
+            if let Some(mut cycle) = cycle {
+                cycle.push(self.id.clone());
+
+                context.notify(Event::Cycle(self.id.clone()));
+                context.close_cycle(cycle.clone());
+                return Resolved::success(path.closing(cycle));
+            }
+        }
+
+        let path = path.append(self);
+        context.notify(Event::Entered(self.id.clone()));
+
+        match Self::cached_solvability(&path, context) {
             Solvability::Ok => {
-                match self.dependency {
+                let resolved = match self.dependency {
                     Some(ref dependency) => {
-                        let subresult = dependency.resolve(path);
+                        let outer = context.enter(&path);
+                        let subresult = dependency.resolve(path.clone(), context);
+                        context.leave(outer);
                         Resolved::new(
                             subresult.paths,
                             subresult.cause.above(self))
                     },
-                    None => Resolved::success(path)
+                    None => Resolved::success(path.clone())
+                };
+                // Results cut short by `wanted` only hold for this call, and
+                // cycles closed above the node depend on how it was reached
+                let complete = resolved.paths.len() < context.wanted.unwrap_or(usize::MAX);
+                let relative = !context.allow_cycles || !Self::closes_above(&path, &resolved);
+                if complete && relative && !context.is_aborted() {
+                    context.cache.insert(&path, &resolved);
                 }
+
+                match resolved.paths.len() {
+                    0 => context.notify(Event::Exhausted(self.id.clone())),
+                    n => context.notify(Event::Solved(self.id.clone(), n))
+                }
+                resolved
             },
-            Solvability::Conflict => {
-                let cause = match path.unique(&self) {
-                    true => Cause::empty(),
-                    false => Cause::from(self)
-                };
-                Resolved::failure(cause)
+            Solvability::Cached => {
+                context.notify(Event::Cached(self.id.clone()));
+                context.cache.get(&path).unwrap()
             },
-            _ => unreachable!()
+            Solvability::Conflict => {
+                context.notify(Event::Conflict(self.id.clone()));
+                Resolved::failure(Self::conflict_cause(&path))
+            }
+        }
+    }
+
+    /// Checks if some path of `resolved` closes a new cycle starting above
+    /// the last node of `path`.
+    fn closes_above<'a>(path: &Path<'a, R>, resolved: &Resolved<'a, R>) -> bool {
+        let above = &path.nodes[..path.nodes.len() - 1];
+
+        resolved.paths
+            .iter()
+            .flat_map(|solved| solved.cycles.iter())
+            .filter(|cycle| !path.cycles.contains(cycle))
+            .any(|cycle| above.iter().any(|node| node.id == cycle[0]))
+    }
+
+    /// Ids this node declares it can't be selected with.
+    pub fn exclusions(&self) -> Vec<R::Id> {
+        match self.dependency {
+            Some(ref dependency) => dependency.exclusions(),
+            None => vec![]
+        }
+    }
+
+    /// Cause of the conflict of the last node of `path`: the node itself if
+    /// it appears twice, and the node declaring an exclusion, if any.
+    pub(crate) fn conflict_cause<'a>(path: &Path<'a, R>) -> Cause<'a, R> {
+        let node = *path.nodes.last().expect("cannot find the cause of an empty path");
+
+        let cause = match path.unique(node) {
+            true => Cause::empty(),
+            false => Cause::from(node)
+        };
+
+        match path.excluding() {
+            Some(declaring) => cause.add(declaring),
+            None => cause
         }
     }
 
@@ -53,4 +324,13 @@ impl <R: Resolvable> Node<R> {
             Solvability::Ok
         }
     }
+
+    /// Like `solvability`, but also checks if the last node of `path` has
+    /// already been solved under an equivalent prefix.
+    pub fn cached_solvability<'a>(path: &Path<'a, R>, context: &mut Context<'a, R>) -> Solvability {
+        match Self::solvability(path) {
+            Solvability::Ok if context.cache.contains(path) => Solvability::Cached,
+            solvability => solvability
+        }
+    }
 }
@@ -0,0 +1,40 @@
+use budget::Aborted;
+use error::Error;
+use super::resolvable::Resolvable;
+use super::resolved::Resolved;
+
+/// Result of a resolution that may run out of budget.
+#[derive(Debug, PartialEq)]
+pub enum Outcome<'a, R: 'a + Resolvable> {
+    /// The search completed: the paths found are all there are, or the
+    /// requested number of them
+    Finished(Resolved<'a, R>),
+    /// The search stopped early, nothing can be said about solvability
+    Aborted(Aborted)
+}
+
+impl<'a, R: 'a + Resolvable> Outcome<'a, R> {
+    pub fn is_aborted(&self) -> bool {
+        match self {
+            Outcome::Aborted(_) => true,
+            Outcome::Finished(_) => false
+        }
+    }
+
+    /// The resolution, if the search completed.
+    pub fn finished(self) -> Option<Resolved<'a, R>> {
+        match self {
+            Outcome::Finished(resolved) => Some(resolved),
+            Outcome::Aborted(_) => None
+        }
+    }
+
+    /// The resolution, or `Error::BudgetExceeded` if the search stopped
+    /// early.
+    pub fn into_result(self) -> Result<Resolved<'a, R>, Error<R::Id>> {
+        match self {
+            Outcome::Finished(resolved) => Ok(resolved),
+            Outcome::Aborted(aborted) => Err(aborted.into())
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use std::mem;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::thread;
+
+use context::Context;
+use observer::Event;
+use observer::Observer;
+use observer::Silent;
+use path::Path;
+use super::Node;
+use super::cause::Cause;
+use super::resolvable::Resolvable;
+use super::resolved::Resolved;
+use super::solvability::Solvability;
+
+/// Observer forwarding the events of all workers to the same observer.
+struct Shared<Id>(Arc<Mutex<Box<dyn Observer<Id>>>>);
+
+impl<Id> Observer<Id> for Shared<Id> {
+    fn notify(&mut self, event: &Event<Id>) {
+        self.0.lock().unwrap().notify(event);
+    }
+}
+
+/// Solves the alternatives of `node` on up to `workers` threads.
+///
+/// Like `iterative::solve`, assumes the dependency resolves its alternatives
+/// the way `Resolvable::alternatives` describes them. Each alternative is
+/// solved with `Node::solve_in` in a context of its own, and the results are
+/// merged in the order of the alternatives, so they don't depend on
+/// scheduling. Only the order of the observed events does.
+///
+/// Only the alternatives of `node` itself are spread over threads, deeper
+/// ones are solved sequentially by the worker that picked them up. Workers
+/// don't share a cache either, each one starts with an empty one, so a
+/// subproblem met by several of them is solved by each.
+///
+/// With a limit, workers stop picking up alternatives once the ones before
+/// them have found enough paths, as later ones can't make it to the result.
+pub fn solve<'a, R>(node: &'a Node<R>, path: Path<'a, R>, context: &mut Context<'a, R>, workers: usize) -> Resolved<'a, R>
+    where R: Resolvable + Send + Sync,
+          R::Id: 'static + Send + Sync
+{
+    let alternatives = match node.dependency {
+        Some(ref dependency) => dependency.alternatives(),
+        None => vec![]
+    };
+
+    let appended = path.clone().append(node);
+    let sequential = workers < 2
+        || alternatives.len() < 2
+        || context.depth > 0
+        || context.limit == Some(0)
+        || matches!(Node::solvability(&appended), Solvability::Conflict);
+
+    if sequential {
+        return node.solve_in(path, context);
+    }
+    let path = appended;
+
+    if !context.proceed() {
+        return Resolved::failure(Cause::empty());
+    }
+    context.notify(Event::Entered(node.id.clone()));
+
+    let observer = mem::replace(&mut context.observer, Box::new(Silent));
+    let observer = Arc::new(Mutex::new(observer));
+    let next = AtomicUsize::new(0);
+    let limit = context.limit;
+    let found = Mutex::new(vec![None; alternatives.len()]);
+
+    let mut forks: Vec<_> = (0..workers.min(alternatives.len()))
+        .map(|_| {
+            let mut fork = context.fork(Shared(observer.clone()));
+            fork.entered = path.nodes.clone();
+            fork
+        })
+        .collect();
+
+    let mut solved: Vec<(usize, Resolved<'a, R>)> = thread::scope(|scope| {
+        let handles: Vec<_> = forks
+            .iter_mut()
+            .map(|fork| {
+                let (alternatives, path, next, found) = (&alternatives, &path, &next, &found);
+
+                scope.spawn(move || {
+                    let mut solved = vec![];
+
+                    loop {
+                        if enough(&found.lock().unwrap(), limit) {
+                            return solved;
+                        }
+
+                        let index = next.fetch_add(1, Ordering::SeqCst);
+                        match alternatives.get(index) {
+                            Some(alternative) => {
+                                let resolved = Node::solve_together(alternative, path.clone(), limit, fork);
+                                found.lock().unwrap()[index] = Some(resolved.paths.len());
+                                solved.push((index, resolved));
+                            },
+                            None => return solved
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    });
+
+    for fork in forks {
+        context.join(fork);
+    }
+    context.observer = match Arc::try_unwrap(observer) {
+        Ok(observer) => observer.into_inner().unwrap(),
+        Err(_) => unreachable!("workers have all been joined")
+    };
+
+    // Like `Resolved::merge_wanted` in order, so that the alternatives past
+    // the limit leave out their causes too
+    solved.sort_by_key(|&(index, _)| index);
+    let merged = Resolved::merge_wanted(solved.into_iter().map(|(_, resolved)| resolved), context.limit);
+
+    match merged.paths.len() {
+        0 => context.notify(Event::Exhausted(node.id.clone())),
+        n => context.notify(Event::Solved(node.id.clone(), n))
+    }
+
+    Resolved::new(merged.paths, merged.cause.above(node))
+}
+
+/// Whether the alternatives solved so far without a gap from the first one
+/// hold at least `limit` paths.
+fn enough(found: &[Option<usize>], limit: Option<usize>) -> bool {
+    let limit = match limit {
+        Some(limit) => limit,
+        None => return false
+    };
+
+    let solved: usize = found
+        .iter()
+        .take_while(|count| count.is_some())
+        .map(|count| count.unwrap())
+        .sum();
+    solved >= limit
+}
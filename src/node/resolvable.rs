@@ -3,12 +3,42 @@ use std::cmp::PartialEq;
 use std::fmt::Debug;
 use std::hash::Hash;
 
+use context::Context;
 use package::ident::Ident;
 use path::Path;
+use super::Node;
 use super::resolved::Resolved;
 
 pub trait Resolvable: Sized + Clone + Debug + Eq + Hash + PartialEq {
     type Id: Ident;
 
-    fn resolve<'a>(&'a self, path: Path<'a, Self>) -> Resolved<'a, Self>;
+    fn resolve<'a>(&'a self, path: Path<'a, Self>, context: &mut Context<'a, Self>) -> Resolved<'a, Self>;
+
+    /// Sets of nodes satisfying this dependency, in order of preference.
+    /// All nodes of a set have to be solved together.
+    ///
+    /// Must describe the same dependency `resolve` solves.
+    fn alternatives(&self) -> Vec<Vec<&Node<Self>>>;
+
+    /// Ids the node owning this dependency declares it can't be selected
+    /// with, on top of those its own id conflicts with.
+    fn exclusions(&self) -> Vec<Self::Id> {
+        vec![]
+    }
+
+    /// Checks if a node selected again is satisfied by the copy already in
+    /// the path, rather than conflicting with it, so that nodes can share
+    /// dependencies. The nodes of an alternative are then solved in turn,
+    /// see `Node::solve_together`.
+    fn shares_nodes() -> bool {
+        false
+    }
+
+    /// Nodes this dependency may descend into.
+    fn nodes(&self) -> Vec<&Node<Self>> {
+        self.alternatives()
+            .into_iter()
+            .flatten()
+            .collect()
+    }
 }
@@ -25,15 +25,30 @@ impl<'a, T: 'a + Resolvable> Resolved<'a, T> {
     }
 
     pub fn is_success(&self) -> bool {
-        self.paths.len() != 0
+        !self.paths.is_empty()
     }
 
     pub fn merge(elements: Vec<Resolved<'a, T>>) -> Self {
-        elements
-            .into_iter()
-            .fold(
-                Resolved::new(vec![], Cause::empty()),
-                Self::merge_two)
+        Self::merge_wanted(elements, None)
+    }
+
+    /// Like `merge`, but stops taking elements once `wanted` paths are
+    /// found, all of them if `None`. Elements are only produced as needed.
+    pub fn merge_wanted<I: IntoIterator<Item = Resolved<'a, T>>>(elements: I, wanted: Option<usize>) -> Self {
+        let mut elements = elements.into_iter();
+        let mut merged = Resolved::new(vec![], Cause::empty());
+
+        while merged.paths.len() < wanted.unwrap_or(usize::MAX) {
+            match elements.next() {
+                Some(element) => merged = Self::merge_two(merged, element),
+                None => break
+            }
+        }
+
+        if let Some(wanted) = wanted {
+            merged.paths.truncate(wanted);
+        }
+        merged
     }
 
     fn merge_two(left: Self, right: Self) -> Self {
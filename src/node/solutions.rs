@@ -0,0 +1,209 @@
+use path::Path;
+use super::Node;
+use super::resolvable::Resolvable;
+use super::solvability::Solvability;
+
+/// Node still to be visited, with the positions of its ancestors in the
+/// path.
+type Pending<'a, R> = (&'a Node<R>, Vec<usize>);
+
+/// Partial solution: a path and the nodes still to be visited, next on top.
+struct State<'a, R: 'a + Resolvable> {
+    path: Path<'a, R>,
+    pending: Vec<Pending<'a, R>>
+}
+
+/// Lazy, depth-first enumeration of the paths solving a node.
+///
+/// Yields the same paths as `Node::solve`, in the same order, but only
+/// explores as much of the tree as needed to produce the next one.
+/// Memory is bounded by the depth of the tree times its branching.
+pub struct Solutions<'a, R: 'a + Resolvable> {
+    stack: Vec<State<'a, R>>,
+    allow_cycles: bool
+}
+
+impl<'a, R: 'a + Resolvable> Solutions<'a, R> {
+    pub fn new(node: &'a Node<R>, path: Path<'a, R>) -> Self {
+        let root = State {
+            pending: vec![(node, (0..path.nodes.len()).collect())],
+            path
+        };
+
+        Solutions {
+            stack: vec![root],
+            allow_cycles: false
+        }
+    }
+
+    /// Accepts dependency cycles, like `Context::allowing_cycles`.
+    pub fn allowing_cycles(mut self) -> Self {
+        self.allow_cycles = true;
+        self
+    }
+}
+
+/// Outcome of visiting a single node of the search.
+pub enum Progress<'a, R: 'a + Resolvable> {
+    Found(Path<'a, R>),
+    Searching,
+    Done
+}
+
+impl<'a, R: 'a + Resolvable> Solutions<'a, R> {
+    /// Visits the next node, so callers may stop in between.
+    pub fn advance(&mut self) -> Progress<'a, R> {
+        let State { path, mut pending } = match self.stack.pop() {
+            Some(state) => state,
+            None => return Progress::Done
+        };
+
+        let (node, ancestors) = match pending.pop() {
+            Some(next) => next,
+            None => return Progress::Found(path)
+        };
+
+        // A shared node already selected satisfies itself
+        if R::shares_nodes() && path.has(node) {
+            self.stack.push(State { path, pending });
+            return Progress::Searching;
+        }
+
+        if self.allow_cycles {
+            if let Some(start) = ancestors.iter().position(|&i| path.nodes[i].id == node.id) {
+                let mut cycle: Vec<_> = ancestors[start..].iter().map(|&i| path.nodes[i].id.clone()).collect();
+                cycle.push(node.id.clone());
+
+                self.stack.push(State { path: path.closing(cycle), pending });
+                return Progress::Searching;
+            }
+        }
+
+        let position = path.nodes.len();
+        let path = path.append(node);
+
+        if let Solvability::Conflict = Node::solvability(&path) {
+            return Progress::Searching;
+        }
+
+        match node.dependency {
+            Some(ref dependency) => {
+                let mut below = ancestors;
+                below.push(position);
+
+                // Push in reverse, so that the preferred alternative is explored first
+                for alternative in dependency.alternatives().into_iter().rev() {
+                    let mut pending = pending.clone();
+                    pending.extend(alternative.into_iter().rev().map(|child| (child, below.clone())));
+
+                    self.stack.push(State {
+                        path: path.clone(),
+                        pending
+                    });
+                }
+            },
+            None => self.stack.push(State { path, pending })
+        }
+
+        Progress::Searching
+    }
+}
+
+impl<'a, R: 'a + Resolvable> Iterator for Solutions<'a, R> {
+    type Item = Path<'a, R>;
+
+    fn next(&mut self) -> Option<Path<'a, R>> {
+        loop {
+            match self.advance() {
+                Progress::Found(path) => return Some(path),
+                Progress::Searching => {},
+                Progress::Done => return None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use context::Context;
+    use node::resolved::Resolved;
+    use package::ident::SimpleUnique;
+    use super::*;
+
+    type N = Node<MockResolvable>;
+
+    #[derive(Clone, Debug, Eq, Hash, PartialEq)]
+    struct MockResolvable {
+        alternatives: Vec<Vec<N>>
+    }
+
+    impl Resolvable for MockResolvable {
+        type Id = SimpleUnique;
+
+        fn resolve<'a>(&'a self, _path: Path<'a, Self>, _context: &mut Context<'a, Self>) -> Resolved<'a, Self> {
+            unimplemented!();
+        }
+
+        fn alternatives(&self) -> Vec<Vec<&N>> {
+            self.alternatives
+                .iter()
+                .map(|nodes| nodes.iter().collect())
+                .collect()
+        }
+    }
+
+    fn node(id: &'static str, alternatives: Option<Vec<Vec<N>>>) -> N {
+        Node {
+            id: SimpleUnique { id },
+            dependency: alternatives.map(|alternatives| MockResolvable { alternatives })
+        }
+    }
+
+    fn ids(path: Path<MockResolvable>) -> Vec<&'static str> {
+        path.nodes.iter().map(|n| n.id.id).collect()
+    }
+
+    #[test]
+    fn enumerates_in_preference_order() {
+        // a -> (b & c) | d, b -> e | f
+        let (c, d, e, f) = (node("c", None), node("d", None), node("e", None), node("f", None));
+        let b = node("b", Some(vec![vec![e], vec![f]]));
+        let a = node("a", Some(vec![vec![b, c], vec![d]]));
+
+        let solutions: Vec<_> = Solutions::new(&a, Path::new(vec![]))
+            .map(ids)
+            .collect();
+
+        assert_eq!(
+            solutions,
+            vec![
+                vec!["a", "b", "e", "c"],
+                vec!["a", "b", "f", "c"],
+                vec!["a", "d"]]);
+    }
+
+    #[test]
+    fn skips_conflicting_branches() {
+        // a -> (b & c) | c, b -> c
+        let c = node("c", None);
+        let b = node("b", Some(vec![vec![c.clone()]]));
+        let a = node("a", Some(vec![vec![b, c.clone()], vec![c]]));
+
+        let solutions: Vec<_> = Solutions::new(&a, Path::new(vec![]))
+            .map(ids)
+            .collect();
+
+        assert_eq!(
+            solutions,
+            vec![vec!["a", "c"]]);
+    }
+
+    #[test]
+    fn exhausts_without_alternatives() {
+        let a = node("a", Some(vec![]));
+
+        assert_eq!(
+            Solutions::new(&a, Path::new(vec![])).next(),
+            None);
+    }
+}
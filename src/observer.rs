@@ -0,0 +1,88 @@
+use std::sync::Arc;
+use std::sync::Mutex;
+
+/// Step of a resolution, as seen by an `Observer`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Event<Id> {
+    /// The node is being solved
+    Entered(Id),
+    /// The node conflicts with the path leading to it
+    Conflict(Id),
+    /// The node was already selected by the path leading to it, and
+    /// cycles are allowed
+    Cycle(Id),
+    /// The node was already solved under an equivalent path
+    Cached(Id),
+    /// No alternative of the node's dependency could be satisfied
+    Exhausted(Id),
+    /// The node was solved, with the given number of paths
+    Solved(Id, usize)
+}
+
+/// Receives the events of a resolution.
+pub trait Observer<Id>: Send {
+    fn notify(&mut self, event: &Event<Id>);
+}
+
+/// Observer ignoring all events.
+#[derive(Clone, Debug, Default)]
+pub struct Silent;
+
+impl<Id> Observer<Id> for Silent {
+    fn notify(&mut self, _event: &Event<Id>) {}
+}
+
+/// Observer collecting all events.
+///
+/// Clones share the same log, so one can be kept to read the events
+/// while another is handed to the solver.
+#[derive(Clone, Debug)]
+pub struct Recorder<Id> {
+    log: Arc<Mutex<Vec<Event<Id>>>>
+}
+
+impl<Id: Clone> Recorder<Id> {
+    pub fn new() -> Self {
+        Recorder { log: Arc::new(Mutex::new(vec![])) }
+    }
+
+    /// Events received so far, oldest first.
+    pub fn events(&self) -> Vec<Event<Id>> {
+        self.log.lock().unwrap().clone()
+    }
+
+    pub fn clear(&self) {
+        self.log.lock().unwrap().clear();
+    }
+}
+
+impl<Id: Clone> Default for Recorder<Id> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Id: Clone + Send> Observer<Id> for Recorder<Id> {
+    fn notify(&mut self, event: &Event<Id>) {
+        self.log.lock().unwrap().push(event.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_shared_log() {
+        let recorder = Recorder::new();
+        let mut observer = recorder.clone();
+
+        observer.notify(&Event::Entered("a"));
+        observer.notify(&Event::Solved("a", 1));
+
+        assert_eq!(recorder.events(), vec![Event::Entered("a"), Event::Solved("a", 1)]);
+
+        recorder.clear();
+        assert_eq!(observer.events(), vec![]);
+    }
+}
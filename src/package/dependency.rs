@@ -1,14 +1,188 @@
 use package::ident::Ident;
+use package::marker::Environment;
+use package::marker::Marker;
+use package::requirement::Requirement;
+
+/// What a dependency is needed for.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Scope {
+    /// At run time
+    Normal,
+    /// To build the declaring package, on the host running the build
+    Build,
+    /// To develop the declaring package, only for the root
+    Dev,
+    /// To test the declaring package, only for the root
+    Test
+}
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum Dependency<Id: Ident> {
-    Choice(Vec<Id>)
+    Choice(Vec<Id>),
+    /// Any version of the named package matching the requirement, newest
+    /// first. Only matches ids with a version.
+    Range(String, Requirement),
+    /// Any provider of the named virtual package, after any version of a
+    /// package by that name, newest first.
+    Virtual(String),
+    /// The declaring package can't be selected along with any match of the
+    /// dependency.
+    Conflicts(Box<Dependency<Id>>),
+    /// Same as `Conflicts` for the solver, but says the declaring package
+    /// breaks the matches rather than being unable to coexist with them.
+    Breaks(Box<Dependency<Id>>),
+    /// Turns on the named features of the matches it selects.
+    WithFeatures(Box<Dependency<Id>>, Vec<String>),
+    /// Only applies in the environments where the marker holds.
+    When(Box<Dependency<Id>>, Marker),
+    /// Needed in a scope other than `Normal`.
+    Scoped(Box<Dependency<Id>>, Scope),
+    /// Satisfied if possible, before any `Suggests`, and skipped otherwise.
+    Recommends(Box<Dependency<Id>>),
+    /// Satisfied if possible once recommendations are, skipped otherwise.
+    Suggests(Box<Dependency<Id>>)
 }
 
 impl<Id: Ident> Dependency<Id> {
+    pub fn range(name: &str, requirement: Requirement) -> Self {
+        Dependency::Range(name.to_string(), requirement)
+    }
+
+    pub fn provider(name: &str) -> Self {
+        Dependency::Virtual(name.to_string())
+    }
+
+    /// Declared by the providers of a virtual package that only one of them
+    /// may provide at a time. Packages never exclude themselves.
+    pub fn exclusive(name: &str) -> Self {
+        Self::conflicts(Self::provider(name))
+    }
+
+    pub fn conflicts(dependency: Dependency<Id>) -> Self {
+        Dependency::Conflicts(Box::new(dependency))
+    }
+
+    pub fn breaks(dependency: Dependency<Id>) -> Self {
+        Dependency::Breaks(Box::new(dependency))
+    }
+
+    /// Same dependency, also turning on `features` of its matches.
+    pub fn with_features(self, features: &[&str]) -> Self {
+        let features = features.iter().map(|name| name.to_string()).collect();
+        Dependency::WithFeatures(Box::new(self), features)
+    }
+
+    /// Same dependency, only applying where `marker` holds.
+    pub fn when(self, marker: Marker) -> Self {
+        Dependency::When(Box::new(self), marker)
+    }
+
+    /// Same dependency, needed in `scope`.
+    pub fn in_scope(self, scope: Scope) -> Self {
+        Dependency::Scoped(Box::new(self), scope)
+    }
+
+    pub fn recommends(dependency: Dependency<Id>) -> Self {
+        Dependency::Recommends(Box::new(dependency))
+    }
+
+    pub fn suggests(dependency: Dependency<Id>) -> Self {
+        Dependency::Suggests(Box::new(dependency))
+    }
+
+    /// Ids listed by the dependency. Ranges and virtual packages list none,
+    /// as they depend on the packages in a repository, see `target`, and
+    /// neither do negative dependencies, as nothing satisfies them.
     pub fn ids(&self) -> Vec<Id> {
         match self {
-            &Dependency::Choice(ref ids) => ids.clone()
+            Dependency::Choice(ids) => ids.clone(),
+            Dependency::WithFeatures(dependency, _) | Dependency::When(dependency, _) => dependency.ids(),
+            Dependency::Scoped(dependency, _) => dependency.ids(),
+            Dependency::Recommends(dependency) | Dependency::Suggests(dependency) => dependency.ids(),
+            _ => vec![]
+        }
+    }
+
+    /// What a range or a virtual dependency asks for, as a reader would
+    /// write it, `None` for explicit choices.
+    pub fn target(&self) -> Option<String> {
+        match self {
+            Dependency::Choice(_) => None,
+            Dependency::Range(name, requirement) => Some(format!("{} {}", name, requirement)),
+            Dependency::Virtual(name) => Some(format!("a provider of {}", name)),
+            Dependency::Conflicts(dependency) | Dependency::Breaks(dependency) => dependency.target(),
+            Dependency::WithFeatures(dependency, _) | Dependency::When(dependency, _) => dependency.target(),
+            Dependency::Scoped(dependency, _) => dependency.target(),
+            Dependency::Recommends(dependency) | Dependency::Suggests(dependency) => dependency.target()
+        }
+    }
+
+    /// Dependency whose matches are ruled out, if this one is negative.
+    pub fn negated(&self) -> Option<&Dependency<Id>> {
+        match self {
+            Dependency::Conflicts(dependency) | Dependency::Breaks(dependency) => Some(dependency),
+            Dependency::When(dependency, _) | Dependency::Scoped(dependency, _) => dependency.negated(),
+            _ => None
+        }
+    }
+
+    /// Dependency to satisfy if possible, if this one is weak.
+    pub fn weakened(&self) -> Option<&Dependency<Id>> {
+        match self {
+            Dependency::Recommends(dependency) | Dependency::Suggests(dependency) => Some(dependency),
+            Dependency::When(dependency, _) | Dependency::Scoped(dependency, _) => dependency.weakened(),
+            _ => None
+        }
+    }
+
+    /// Features the dependency turns on in its matches.
+    pub fn features(&self) -> Vec<String> {
+        match self {
+            Dependency::WithFeatures(dependency, features) => {
+                let mut all = dependency.features();
+                all.extend(features.iter().cloned());
+                all
+            },
+            Dependency::Recommends(dependency) | Dependency::Suggests(dependency) => dependency.features(),
+            Dependency::When(dependency, _) | Dependency::Scoped(dependency, _) => dependency.features(),
+            _ => vec![]
+        }
+    }
+
+    /// Checks if all the markers of the dependency hold in `environment`.
+    pub fn applies(&self, environment: &Environment) -> bool {
+        match self {
+            Dependency::When(dependency, marker) => marker.evaluate(environment) && dependency.applies(environment),
+            Dependency::WithFeatures(dependency, _)
+                | Dependency::Scoped(dependency, _)
+                | Dependency::Conflicts(dependency)
+                | Dependency::Breaks(dependency)
+                | Dependency::Recommends(dependency)
+                | Dependency::Suggests(dependency) => dependency.applies(environment),
+            _ => true
+        }
+    }
+
+    /// Scope the dependency is needed in, the outermost one given or
+    /// `Normal`.
+    pub fn scope(&self) -> Scope {
+        match self {
+            Dependency::Scoped(_, scope) => *scope,
+            Dependency::WithFeatures(dependency, _)
+                | Dependency::When(dependency, _)
+                | Dependency::Conflicts(dependency)
+                | Dependency::Breaks(dependency)
+                | Dependency::Recommends(dependency)
+                | Dependency::Suggests(dependency) => dependency.scope(),
+            _ => Scope::Normal
+        }
+    }
+
+    /// Same dependency without its outer markers and scope.
+    pub fn unconditional(&self) -> &Dependency<Id> {
+        match self {
+            Dependency::When(dependency, _) | Dependency::Scoped(dependency, _) => dependency.unconditional(),
+            _ => self
         }
     }
 }
@@ -0,0 +1,20 @@
+use package::dependency::Dependency;
+use package::ident::Ident;
+
+/// Optional part of a package, Cargo style.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Feature<Id: Ident> {
+    /// Dependencies the feature adds to those of the package
+    pub dependencies: Vec<Dependency<Id>>,
+    /// Other features of the package it turns on
+    pub enables: Vec<String>
+}
+
+impl<Id: Ident> Feature<Id> {
+    pub fn new(dependencies: Vec<Dependency<Id>>, enables: &[&str]) -> Self {
+        Feature {
+            dependencies,
+            enables: enables.iter().map(|name| name.to_string()).collect()
+        }
+    }
+}
@@ -0,0 +1,288 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+
+use error::Error;
+use package::dependency::Dependency;
+use package::ident::Ident;
+
+/// Position of a node in a `Graph`, which never changes once added.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct NodeIndex(pub usize);
+
+/// Edge from a package to a match of one of its dependencies.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Edge<Id: Ident> {
+    pub source: NodeIndex,
+    pub target: NodeIndex,
+    pub dependency: Dependency<Id>
+}
+
+/// Dependency graph whose nodes live in a single vector and refer to each
+/// other by index, so that cycles are fine to build, compare and print.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Graph<Id: Ident> {
+    nodes: Vec<Id>,
+    indices: HashMap<Id, NodeIndex>,
+    edges: Vec<Edge<Id>>,
+    /// Positions in `edges` of the edges leaving and entering each node
+    outgoing: Vec<Vec<usize>>,
+    incoming: Vec<Vec<usize>>
+}
+
+impl<Id: Ident> Graph<Id> {
+    pub fn new() -> Self {
+        Graph {
+            nodes: vec![],
+            indices: HashMap::new(),
+            edges: vec![],
+            outgoing: vec![],
+            incoming: vec![]
+        }
+    }
+
+    /// Index of the node for `id`, added if it isn't there yet.
+    pub fn add_node(&mut self, id: Id) -> NodeIndex {
+        if let Some(&index) = self.indices.get(&id) {
+            return index;
+        }
+
+        let index = NodeIndex(self.nodes.len());
+        self.nodes.push(id.clone());
+        self.indices.insert(id, index);
+        self.outgoing.push(vec![]);
+        self.incoming.push(vec![]);
+        index
+    }
+
+    /// Adds an edge from `source` to `target`, for `dependency` of `source`.
+    /// Fails if either node is not in the graph.
+    pub fn add_edge(&mut self, source: NodeIndex, target: NodeIndex, dependency: Dependency<Id>) -> Result<(), Error<Id>> {
+        if let Some(&missing) = [source, target].iter().find(|index| index.0 >= self.nodes.len()) {
+            return Err(Error::MissingNode(missing));
+        }
+
+        self.outgoing[source.0].push(self.edges.len());
+        self.incoming[target.0].push(self.edges.len());
+        self.edges.push(Edge { source, target, dependency });
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn index(&self, id: &Id) -> Option<NodeIndex> {
+        self.indices.get(id).cloned()
+    }
+
+    pub fn id(&self, index: NodeIndex) -> Option<&Id> {
+        self.nodes.get(index.0)
+    }
+
+    /// Nodes in the order they were added.
+    pub fn nodes(&self) -> impl Iterator<Item = (NodeIndex, &Id)> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (NodeIndex(i), id))
+    }
+
+    /// Edges in the order they were added.
+    pub fn edges(&self) -> &[Edge<Id>] {
+        &self.edges
+    }
+
+    /// Edges leaving `index`, in the order they were added.
+    pub fn dependencies(&self, index: NodeIndex) -> Vec<&Edge<Id>> {
+        self.outgoing
+            .get(index.0)
+            .into_iter()
+            .flatten()
+            .map(|&edge| &self.edges[edge])
+            .collect()
+    }
+
+    /// Edges entering `index`, in the order they were added.
+    pub fn dependents(&self, index: NodeIndex) -> Vec<&Edge<Id>> {
+        self.incoming
+            .get(index.0)
+            .into_iter()
+            .flatten()
+            .map(|&edge| &self.edges[edge])
+            .collect()
+    }
+
+    /// Nodes reachable from `start`, each once, in depth-first preorder
+    /// following the edges in order.
+    pub fn depth_first(&self, start: NodeIndex) -> Vec<NodeIndex> {
+        let mut visited = HashSet::new();
+        let mut order = vec![];
+        // Nodes left to visit, next on top
+        let mut pending = vec![start];
+
+        while let Some(index) = pending.pop() {
+            if index.0 < self.nodes.len() && visited.insert(index) {
+                order.push(index);
+                pending.extend(self.dependencies(index).into_iter().rev().map(|edge| edge.target));
+            }
+        }
+
+        order
+    }
+
+    /// Nodes reachable from `start`, each once, closest first.
+    pub fn breadth_first(&self, start: NodeIndex) -> Vec<NodeIndex> {
+        let mut visited = HashSet::new();
+        let mut order = vec![];
+        let mut pending = VecDeque::new();
+
+        if start.0 < self.nodes.len() {
+            visited.insert(start);
+            pending.push_back(start);
+        }
+
+        while let Some(index) = pending.pop_front() {
+            order.push(index);
+
+            for edge in self.dependencies(index) {
+                if visited.insert(edge.target) {
+                    pending.push_back(edge.target);
+                }
+            }
+        }
+
+        order
+    }
+
+    /// Nodes of a cycle reachable from `start`, starting and ending with
+    /// the same node, if there is one.
+    pub fn find_cycle(&self, start: NodeIndex) -> Option<Vec<NodeIndex>> {
+        if start.0 >= self.nodes.len() {
+            return None;
+        }
+
+        let mut done = HashSet::new();
+        // Current path, with the position of the next edge to follow
+        let mut stack = vec![(start, 0)];
+
+        while let Some(&mut (index, ref mut next)) = stack.last_mut() {
+            let target = self.outgoing[index.0].get(*next).map(|&edge| self.edges[edge].target);
+            *next += 1;
+
+            match target {
+                Some(target) if done.contains(&target) => {},
+                Some(target) => match stack.iter().position(|&(on_path, _)| on_path == target) {
+                    Some(position) => {
+                        let mut cycle: Vec<_> = stack[position..].iter().map(|&(index, _)| index).collect();
+                        cycle.push(target);
+                        return Some(cycle);
+                    },
+                    None => stack.push((target, 0))
+                },
+                None => {
+                    done.insert(index);
+                    stack.pop();
+                }
+            }
+        }
+
+        None
+    }
+}
+
+impl<Id: Ident> Default for Graph<Id> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use package::ident::SimpleUnique;
+    use super::*;
+
+    fn choice(id: &'static str) -> Dependency<SimpleUnique> {
+        Dependency::Choice(vec![SimpleUnique::new(id)])
+    }
+
+    /// a -> b, a -> c, b -> d, c -> d, d -> b
+    fn cyclic() -> Graph<SimpleUnique> {
+        let mut graph = Graph::new();
+        for id in ["a", "b", "c", "d"].iter() {
+            graph.add_node(SimpleUnique::new(id));
+        }
+
+        for &(source, target) in [(0, 1), (0, 2), (1, 3), (2, 3), (3, 1)].iter() {
+            let dependency = choice(graph.id(NodeIndex(target)).unwrap().id);
+            graph.add_edge(NodeIndex(source), NodeIndex(target), dependency).unwrap();
+        }
+        graph
+    }
+
+    fn names(graph: &Graph<SimpleUnique>, indices: Vec<NodeIndex>) -> Vec<&'static str> {
+        indices.into_iter().map(|index| graph.id(index).unwrap().id).collect()
+    }
+
+    #[test]
+    fn adds_nodes_once() {
+        let mut graph = cyclic();
+
+        assert_eq!(graph.add_node(SimpleUnique::new("c")), NodeIndex(2));
+        assert_eq!(graph.add_node(SimpleUnique::new("e")), NodeIndex(4));
+        assert_eq!(graph.index(&SimpleUnique::new("e")), Some(NodeIndex(4)));
+        assert_eq!(graph.len(), 5);
+    }
+
+    #[test]
+    fn labels_edges() {
+        let graph = cyclic();
+        let d = graph.index(&SimpleUnique::new("d")).unwrap();
+
+        let dependents: Vec<_> = graph.dependents(d).into_iter().map(|edge| edge.source).collect();
+        assert_eq!(dependents, vec![NodeIndex(1), NodeIndex(2)]);
+        assert_eq!(graph.dependencies(d)[0].dependency, choice("b"));
+    }
+
+    #[test]
+    fn traverses_cycles() {
+        let graph = cyclic();
+
+        assert_eq!(names(&graph, graph.depth_first(NodeIndex(0))), vec!["a", "b", "d", "c"]);
+        assert_eq!(names(&graph, graph.breadth_first(NodeIndex(0))), vec!["a", "b", "c", "d"]);
+        assert_eq!(names(&graph, graph.find_cycle(NodeIndex(0)).unwrap()), vec!["b", "d", "b"]);
+        assert_eq!(graph.find_cycle(NodeIndex(4)), None);
+    }
+
+    #[test]
+    fn compares_and_prints_cycles() {
+        let mut graph = cyclic();
+        assert_eq!(graph, cyclic());
+        assert!(format!("{:?}", graph).contains("source: NodeIndex(3), target: NodeIndex(1)"));
+
+        graph.add_edge(NodeIndex(1), NodeIndex(0), choice("a")).unwrap();
+        assert_ne!(graph, cyclic());
+    }
+
+    #[test]
+    fn finds_no_cycle_in_trees() {
+        let mut graph = Graph::new();
+        let a = graph.add_node(SimpleUnique::new("a"));
+        let b = graph.add_node(SimpleUnique::new("b"));
+        graph.add_edge(a, b, choice("b")).unwrap();
+
+        assert_eq!(graph.find_cycle(a), None);
+    }
+
+    #[test]
+    fn rejects_edges_to_missing_nodes() {
+        let mut graph = cyclic();
+
+        assert_eq!(graph.add_edge(NodeIndex(0), NodeIndex(4), choice("e")), Err(Error::MissingNode(NodeIndex(4))));
+        assert_eq!(graph, cyclic());
+    }
+}
@@ -1,11 +1,31 @@
 use std::cmp::Eq;
 use std::cmp::PartialEq;
 use std::collections::HashSet;
+use std::fmt;
 use std::fmt::Debug;
 use std::hash::Hash;
+use std::marker::PhantomData;
+
+use package::version::Version;
 
 pub trait Ident: Clone + Debug + Eq + Hash + PartialEq {
-    fn are_conflicting(instances: &Vec<Self>) -> bool;
+    fn are_conflicting(instances: &[Self]) -> bool;
+
+    /// Representative of the ids this one conflicts with, if ids conflict
+    /// exactly when they share one. Lets long paths be checked for
+    /// conflicts incrementally.
+    fn conflict_class(&self) -> Option<Self> {
+        None
+    }
+
+    /// Name shared by the versions of a package, if ids have versions.
+    fn name(&self) -> Option<&str> {
+        None
+    }
+
+    fn version(&self) -> Option<&Version> {
+        None
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
@@ -15,18 +35,130 @@ pub struct SimpleUnique {
 
 impl SimpleUnique {
     pub fn new(id: &'static str) -> Self {
-        SimpleUnique { id: id }
+        SimpleUnique { id }
+    }
+}
+
+impl fmt::Display for SimpleUnique {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.id)
     }
 }
 
 impl Ident for SimpleUnique {
-    fn are_conflicting(instances: &Vec<Self>) -> bool {
+    fn are_conflicting(instances: &[Self]) -> bool {
         let mut found = HashSet::new();
 
         !instances
-            .into_iter()
+            .iter()
             .all(move |x| found.insert(x))  // will return False if alrady present
     }
+
+    fn conflict_class(&self) -> Option<Self> {
+        Some(self.clone())
+    }
+}
+
+/// Which versions of a package can be selected together, as a type
+/// parameter of `Versioned`, so that it isn't part of the ids.
+pub trait Compatibility: Clone + Debug + Default + Eq + Hash + Ord {
+    /// Version standing for the versions `version` can't be selected
+    /// along with.
+    fn bucket(version: &Version) -> Version;
+}
+
+/// One version at a time.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Exclusive;
+
+impl Compatibility for Exclusive {
+    fn bucket(_version: &Version) -> Version {
+        Version::new(0, 0, 0)
+    }
+}
+
+/// One version per semver-compatible range, like Cargo: `1.2` and `2.0`
+/// can be selected together, `1.2` and `1.3` can't. Below 1.0, the minor
+/// version makes the range, and below 0.1 the patch.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Semver;
+
+impl Compatibility for Semver {
+    fn bucket(version: &Version) -> Version {
+        match (version.major, version.minor) {
+            (0, 0) => Version::new(0, 0, version.patch),
+            (0, minor) => Version::new(0, minor, 0),
+            (major, _) => Version::new(major, 0, 0)
+        }
+    }
+}
+
+/// Named package at some version. Versions of the same name conflict if
+/// they fall in the same bucket of the compatibility `C`, all of them by
+/// default.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Versioned<C: Compatibility = Exclusive> {
+    pub name: String,
+    pub version: Version,
+    compatibility: PhantomData<C>
+}
+
+impl Versioned {
+    pub fn new(name: &str, version: Version) -> Self {
+        Self::with_compatibility(name, version)
+    }
+}
+
+impl Versioned<Semver> {
+    /// Version semver-compatible with the other versions of its name.
+    pub fn semver(name: &str, version: Version) -> Self {
+        Self::with_compatibility(name, version)
+    }
+}
+
+impl<C: Compatibility> Versioned<C> {
+    pub fn with_compatibility(name: &str, version: Version) -> Self {
+        Versioned {
+            name: name.to_string(),
+            version,
+            compatibility: PhantomData
+        }
+    }
+
+    fn bucket(&self) -> Version {
+        C::bucket(&self.version)
+    }
+}
+
+impl<C: Compatibility> fmt::Display for Versioned<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.name, self.version)
+    }
+}
+
+impl<C: Compatibility> Ident for Versioned<C> {
+    fn are_conflicting(instances: &[Self]) -> bool {
+        let mut found = HashSet::new();
+
+        !instances
+            .iter()
+            .all(move |x| found.insert((&x.name, x.bucket())))
+    }
+
+    fn conflict_class(&self) -> Option<Self> {
+        Some(Versioned {
+            version: self.bucket(),
+            ..self.clone()
+        })
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some(&self.name)
+    }
+
+    fn version(&self) -> Option<&Version> {
+        Some(&self.version)
+    }
 }
 
 #[cfg(test)]
@@ -38,7 +170,41 @@ mod tests {
         let a = SimpleUnique::new("A");
         let b = SimpleUnique::new("B");
 
-        assert!(!SimpleUnique::are_conflicting(&vec![a.clone(), b.clone()]));
-        assert!(SimpleUnique::are_conflicting(&vec![a.clone(), b.clone(), a.clone()]));
+        assert!(!SimpleUnique::are_conflicting(&[a.clone(), b.clone()]));
+        assert!(SimpleUnique::are_conflicting(&[a.clone(), b.clone(), a.clone()]));
+        assert_eq!(a.conflict_class(), Some(a.clone()));
+    }
+
+    #[test]
+    fn tests_versioned_conflicts() {
+        let version = |s: &str| s.parse::<Version>().unwrap();
+        let a1 = Versioned::new("a", version("1.0.0"));
+        let a2 = Versioned::new("a", version("2.0.0-rc.1"));
+        let b1 = Versioned::new("b", version("1.0.0"));
+
+        assert!(!Versioned::are_conflicting(&[a1.clone(), b1.clone()]));
+        assert!(Versioned::are_conflicting(&[a1.clone(), b1.clone(), a2.clone()]));
+        assert_eq!(a1.conflict_class(), a2.conflict_class());
+        assert_eq!(a2.to_string(), "a 2.0.0-rc.1");
+    }
+
+    #[test]
+    fn tests_semver_conflicts() {
+        let semver = |s: &str| Versioned::semver("a", s.parse().unwrap());
+        let conflicting = |a: &str, b: &str| Versioned::are_conflicting(&[semver(a), semver(b)]);
+
+        assert!(!conflicting("1.2.0", "2.0.0"));
+        assert!(conflicting("1.2.0", "1.3.0"));
+        assert!(conflicting("2.0.0-rc.1", "2.1.0"));
+        assert!(!conflicting("0.1.0", "0.2.0"));
+        assert!(conflicting("0.2.1", "0.2.5"));
+        assert!(!conflicting("0.0.1", "0.0.2"));
+        assert!(!Versioned::are_conflicting(&[semver("1.0.0"), Versioned::semver("b", "1.1.0".parse().unwrap())]));
+
+        assert_eq!(semver("1.2.0").conflict_class(), semver("1.3.0").conflict_class());
+        assert_ne!(semver("1.2.0").conflict_class(), semver("2.0.0").conflict_class());
+
+        // The compatibility isn't part of the id
+        assert_eq!(semver("1.2.0"), Versioned::with_compatibility("a", "1.2.0".parse().unwrap()));
     }
 }
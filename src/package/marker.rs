@@ -0,0 +1,341 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use package::version::ParseError;
+
+/// Values describing a target, such as `os`, `arch` or `runtime`.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Environment {
+    pub values: BTreeMap<String, String>
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, key: &str, value: &str) -> Self {
+        self.values.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(|value| value.as_str())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Operator {
+    Equal,
+    NotEqual,
+    Less,
+    LessEq,
+    Greater,
+    GreaterEq
+}
+
+impl Operator {
+    fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            Operator::Equal => ordering == Ordering::Equal,
+            Operator::NotEqual => ordering != Ordering::Equal,
+            Operator::Less => ordering == Ordering::Less,
+            Operator::LessEq => ordering != Ordering::Greater,
+            Operator::Greater => ordering == Ordering::Greater,
+            Operator::GreaterEq => ordering != Ordering::Less
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            Operator::Equal => "==",
+            Operator::NotEqual => "!=",
+            Operator::Less => "<",
+            Operator::LessEq => "<=",
+            Operator::Greater => ">",
+            Operator::GreaterEq => ">="
+        }
+    }
+}
+
+/// Condition on an environment, such as `os == "linux" and arch != "arm"`.
+///
+/// Values are compared as dotted numbers when both are, so that `"3.10"`
+/// comes after `"3.9"`, and as strings otherwise. Keys missing from the
+/// environment compare as empty strings.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Marker {
+    Compare(String, Operator, String),
+    Not(Box<Marker>),
+    All(Vec<Marker>),
+    Any(Vec<Marker>)
+}
+
+impl Marker {
+    pub fn parse(input: &str) -> Result<Self, ParseError> {
+        let tokens = tokenize(input).ok_or_else(|| ParseError::new(input, "invalid token"))?;
+        let mut parser = Parser { tokens, position: 0 };
+
+        let marker = parser.any().ok_or_else(|| ParseError::new(input, "invalid marker"))?;
+        match parser.position == parser.tokens.len() {
+            true => Ok(marker),
+            false => Err(ParseError::new(input, "unexpected trailing input"))
+        }
+    }
+
+    pub fn evaluate(&self, environment: &Environment) -> bool {
+        match self {
+            Marker::Compare(key, operator, value) => {
+                let actual = environment.get(key).unwrap_or("");
+                operator.holds(compare(actual, value))
+            },
+            Marker::Not(marker) => !marker.evaluate(environment),
+            Marker::All(markers) => markers.iter().all(|marker| marker.evaluate(environment)),
+            Marker::Any(markers) => markers.iter().any(|marker| marker.evaluate(environment))
+        }
+    }
+
+    /// Keys the marker looks up.
+    pub fn keys(&self) -> Vec<&str> {
+        match self {
+            Marker::Compare(key, _, _) => vec![key.as_str()],
+            Marker::Not(marker) => marker.keys(),
+            Marker::All(markers) | Marker::Any(markers) => markers.iter().flat_map(|marker| marker.keys()).collect()
+        }
+    }
+}
+
+impl FromStr for Marker {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, ParseError> {
+        Marker::parse(s)
+    }
+}
+
+impl fmt::Display for Marker {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let nested = |marker: &Marker| match marker {
+            Marker::All(_) | Marker::Any(_) => format!("({})", marker),
+            _ => marker.to_string()
+        };
+
+        match self {
+            Marker::Compare(key, operator, value) => write!(f, "{} {} {:?}", key, operator.symbol(), value),
+            Marker::Not(marker) => write!(f, "not {}", nested(marker)),
+            Marker::All(markers) => {
+                let parts: Vec<_> = markers.iter().map(nested).collect();
+                write!(f, "{}", parts.join(" and "))
+            },
+            Marker::Any(markers) => {
+                let parts: Vec<_> = markers
+                    .iter()
+                    .map(|marker| match marker {
+                        Marker::Any(_) => format!("({})", marker),
+                        _ => marker.to_string()
+                    })
+                    .collect();
+                write!(f, "{}", parts.join(" or "))
+            }
+        }
+    }
+}
+
+/// Dotted numbers numerically, anything else as strings.
+fn compare(a: &str, b: &str) -> Ordering {
+    let numbers = |s: &str| s
+        .split('.')
+        .map(|part| part.parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>();
+
+    match (numbers(a), numbers(b)) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        _ => a.cmp(b)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Token {
+    Key(String),
+    Value(String),
+    Operator(Operator),
+    And,
+    Or,
+    Not,
+    Open,
+    Close
+}
+
+fn tokenize(input: &str) -> Option<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            _ if c.is_whitespace() => { chars.next(); },
+            '(' => { chars.next(); tokens.push(Token::Open); },
+            ')' => { chars.next(); tokens.push(Token::Close); },
+            '"' | '\'' => {
+                chars.next();
+                let mut value = String::new();
+                loop {
+                    match chars.next()? {
+                        x if x == c => break,
+                        x => value.push(x)
+                    }
+                }
+                tokens.push(Token::Value(value));
+            },
+            '=' | '!' | '<' | '>' => {
+                chars.next();
+                let equals = chars.peek() == Some(&'=');
+                if equals {
+                    chars.next();
+                }
+
+                let operator = match (c, equals) {
+                    ('=', true) => Operator::Equal,
+                    ('!', true) => Operator::NotEqual,
+                    ('<', false) => Operator::Less,
+                    ('<', true) => Operator::LessEq,
+                    ('>', false) => Operator::Greater,
+                    ('>', true) => Operator::GreaterEq,
+                    _ => return None
+                };
+                tokens.push(Token::Operator(operator));
+            },
+            _ if c.is_ascii_alphanumeric() || c == '_' => {
+                let mut word = String::new();
+                while let Some(&x) = chars.peek() {
+                    if !(x.is_ascii_alphanumeric() || x == '_' || x == '.') {
+                        break;
+                    }
+                    word.push(x);
+                    chars.next();
+                }
+
+                tokens.push(match word.as_str() {
+                    "and" => Token::And,
+                    "or" => Token::Or,
+                    "not" => Token::Not,
+                    _ => Token::Key(word)
+                });
+            },
+            _ => return None
+        }
+    }
+
+    Some(tokens)
+}
+
+/// Recursive descent over the tokens, `or` binding looser than `and`.
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize
+}
+
+impl Parser {
+    fn any(&mut self) -> Option<Marker> {
+        let mut markers = vec![self.all()?];
+        while self.accept(&Token::Or) {
+            markers.push(self.all()?);
+        }
+
+        match markers.len() {
+            1 => markers.pop(),
+            _ => Some(Marker::Any(markers))
+        }
+    }
+
+    fn all(&mut self) -> Option<Marker> {
+        let mut markers = vec![self.unary()?];
+        while self.accept(&Token::And) {
+            markers.push(self.unary()?);
+        }
+
+        match markers.len() {
+            1 => markers.pop(),
+            _ => Some(Marker::All(markers))
+        }
+    }
+
+    fn unary(&mut self) -> Option<Marker> {
+        if self.accept(&Token::Not) {
+            return Some(Marker::Not(Box::new(self.unary()?)));
+        }
+
+        if self.accept(&Token::Open) {
+            let marker = self.any()?;
+            return match self.accept(&Token::Close) {
+                true => Some(marker),
+                false => None
+            };
+        }
+
+        match self.tokens.get(self.position..self.position + 3)? {
+            [Token::Key(key), Token::Operator(operator), Token::Value(value)] => {
+                self.position += 3;
+                Some(Marker::Compare(key.clone(), *operator, value.clone()))
+            },
+            _ => None
+        }
+    }
+
+    fn accept(&mut self, token: &Token) -> bool {
+        let found = self.tokens.get(self.position) == Some(token);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn holds(marker: &str, environment: &Environment) -> bool {
+        marker.parse::<Marker>().unwrap().evaluate(environment)
+    }
+
+    #[test]
+    fn evaluates() {
+        let linux = Environment::new().with("os", "linux").with("arch", "x86_64").with("runtime", "3.10");
+
+        assert!(holds("os == \"linux\" and arch != \"arm\"", &linux));
+        assert!(!holds("os == 'windows' or arch == 'arm'", &linux));
+        assert!(holds("not (os == 'windows' or arch == 'arm')", &linux));
+        assert!(holds("os == 'windows' or arch == 'arm' and runtime < '4' or os == 'linux'", &linux));
+        assert!(!holds("feature == 'gpu'", &linux));
+        assert!(holds("feature != 'gpu'", &linux));
+    }
+
+    #[test]
+    fn compares_numbers() {
+        let runtime = Environment::new().with("runtime", "3.10");
+
+        assert!(holds("runtime >= '3.9'", &runtime));
+        assert!(holds("runtime < '3.10.1'", &runtime));
+        assert!(!holds("runtime > '3.10'", &runtime));
+        assert!(holds("runtime <= 'beta'", &runtime));
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        for input in ["", "os", "os ==", "os == linux", "(os == 'a'", "os == 'a' and", "os => 'a'", "os == 'a' 'b'", "os == 'a"].iter() {
+            assert!(Marker::parse(input).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn displays() {
+        for input in ["os == \"linux\" and arch != \"arm\"", "not (os == \"a\" or os == \"b\")", "(os == \"a\" or os == \"b\") and arch < \"3\""].iter() {
+            assert_eq!(Marker::parse(input).unwrap().to_string(), *input);
+        }
+
+        let marker = Marker::parse("os == 'linux' and (arch == 'arm' or runtime >= '3')").unwrap();
+        assert_eq!(marker.keys(), vec!["os", "arch", "runtime"]);
+    }
+}
@@ -1,5 +1,11 @@
 pub mod dependency;
+pub mod feature;
+pub mod graph;
 pub mod ident;
+pub mod marker;
+#[allow(clippy::module_inception)]
 pub mod package;
-pub mod rawnode;
 pub mod repository;
+pub mod requirement;
+pub mod requirements;
+pub mod version;
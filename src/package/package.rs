@@ -1,11 +1,11 @@
 use std::cmp::Eq;
 use std::cmp::PartialEq;
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::hash::Hash;
 
-use node::Node;
-use node::resolvable::Resolvable;
 use package::dependency::Dependency;
+use package::feature::Feature;
 use package::ident::Ident;
 
 pub trait Package: Clone + Debug + Eq + Hash + PartialEq {
@@ -13,5 +13,15 @@ pub trait Package: Clone + Debug + Eq + Hash + PartialEq {
 
     fn id(&self) -> Self::Id;
     fn dependencies(&self) -> Vec<Dependency<Self::Id>>;
-    fn to_node<R: Resolvable>(&self) -> Node<R>;  // TODO: pass a graph / id + dep_ids
+
+    /// Features that can be turned on, by name.
+    fn features(&self) -> HashMap<String, Feature<Self::Id>> {
+        HashMap::new()
+    }
+
+    /// Virtual packages this one provides, satisfying the dependencies on
+    /// their name.
+    fn provides(&self) -> Vec<String> {
+        vec![]
+    }
 }
@@ -1,85 +1,358 @@
-use std::cell::RefCell;
-use std::rc::Rc;
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::Arc;
 
+use budget::Budget;
+use context::Context;
+use error::Error;
+use node::Node;
 use package::dependency::Dependency;
+use package::dependency::Scope;
+use package::graph::Graph;
+use package::ident::Ident;
+use package::marker::Environment;
 use package::package::Package;
-use package::rawnode::RawNode;
+use package::requirements::Requirements;
+use package::version::Version;
+use path::Path;
+
+/// Node built once and shared by the nodes depending on it.
+type Shared<Id> = Arc<Node<Requirements<Id>>>;
 
 #[derive(Debug)]
 pub struct Repository<P: Package> {
-    packages: HashMap<P::Id, P>
+    packages: HashMap<P::Id, P>,
+    /// Versions of each package name
+    versions: HashMap<String, Vec<P::Id>>,
+    /// Providers of each virtual package
+    providers: HashMap<String, Vec<P::Id>>
 }
 
 impl<P: Package> Repository<P> {
     pub fn new<I>(packages: I) -> Self
     where I: IntoIterator<Item = P> {
-        let packages = packages
-            .into_iter()
-            .map(|p| (p.id(), p))
-            .collect();
+        let mut repository = Repository {
+            packages: HashMap::new(),
+            versions: HashMap::new(),
+            providers: HashMap::new()
+        };
 
-        Repository {
-            packages
+        for pkg in packages {
+            repository.add(pkg);
         }
+        repository
     }
 
+    /// Adds `pkg`, replacing the package with the same id if any.
     pub fn add(&mut self, pkg: P) {
-        self.packages.insert(pkg.id(), pkg);
+        let id = pkg.id();
+        let provides = pkg.provides();
+
+        match self.packages.get(&id) {
+            // Replaced, so only the capabilities it no longer has change
+            Some(replaced) => {
+                for name in replaced.provides() {
+                    if !provides.contains(&name) {
+                        self.drop_provider(&name, &id);
+                    }
+                }
+            },
+            None => {
+                if let Some(name) = id.name() {
+                    self.versions
+                        .entry(name.to_string())
+                        .or_default()
+                        .push(id.clone());
+                }
+            }
+        }
+
+        for name in provides {
+            let providers = self.providers.entry(name).or_default();
+            if !providers.contains(&id) {
+                providers.push(id.clone());
+            }
+        }
+
+        self.packages.insert(id, pkg);
+    }
+
+    fn drop_provider(&mut self, name: &str, id: &P::Id) {
+        if let Some(providers) = self.providers.get_mut(name) {
+            providers.retain(|provider| provider != id);
+            if providers.is_empty() {
+                self.providers.remove(name);
+            }
+        }
     }
 
-    pub fn build_graph(&self, root_id: &P::Id) -> Rc<RefCell<RawNode<P::Id>>> {
-        let mut nodes = HashMap::new();
+    /// Graph of the packages reachable from `root_id`, the root being its
+    /// first node. Panics if the root is not in the repository, see
+    /// `try_build_graph`.
+    pub fn build_graph(&self, root_id: &P::Id) -> Graph<P::Id> {
+        self.graph(root_id, None, false).expect("root is not in the repository")
+    }
+
+    /// Like `build_graph`, keeping only the dependencies that apply in
+    /// `environment`.
+    pub fn build_graph_in(&self, root_id: &P::Id, environment: &Environment) -> Graph<P::Id> {
+        self.graph(root_id, Some(environment), false).expect("root is not in the repository")
+    }
+
+    /// Like `build_graph`, or `build_graph_in` given an environment, but
+    /// fails on a missing root, and on a hard dependency of a reachable
+    /// package that matches nothing.
+    pub fn try_build_graph(&self, root_id: &P::Id, environment: Option<&Environment>) -> Result<Graph<P::Id>, Error<P::Id>> {
+        self.graph(root_id, environment, true)
+    }
+
+    /// Dependencies of `pkg` that apply in `environment`, all of them
+    /// without one.
+    pub fn dependencies_in(&self, pkg: &P, environment: Option<&Environment>) -> Vec<Dependency<P::Id>> {
+        pkg.dependencies()
+            .into_iter()
+            .filter(|dep| match environment {
+                Some(environment) => dep.applies(environment),
+                None => true
+            })
+            .collect()
+    }
 
-        let root = self.get(root_id).unwrap();
+    /// Graph of the packages reachable from `root_id`, checking that hard
+    /// dependencies match something if `strict`.
+    fn graph(&self, root_id: &P::Id, environment: Option<&Environment>, strict: bool) -> Result<Graph<P::Id>, Error<P::Id>> {
+        let root = self.get(root_id).ok_or_else(|| Error::unknown_package(root_id))?;
+        let mut graph = Graph::new();
+        let mut visited = HashSet::new();
         let mut pending = vec![root];
+        graph.add_node(root.id());
 
-        // Aggregate all used packages
-        while !pending.is_empty() {
-            let pkg = pending.pop().unwrap();
-
-            // Check if visited to avoid infinite recursion
-            if !nodes.contains_key(&pkg) {
-                let raw = RawNode::new(pkg.id().clone());
-                nodes.insert(pkg, raw);
-
-                pending.extend(
-                    pkg
-                        .dependencies()
-                        .into_iter()
-                        .flat_map(|dep| self.dependency_matches(&dep))
-                    );
+        // Check if visited to avoid infinite recursion
+        while let Some(pkg) = pending.pop() {
+            if !visited.insert(pkg.id()) {
+                continue;
+            }
+            let source = graph.add_node(pkg.id());
+
+            for dep in self.dependencies_in(pkg, environment) {
+                let matches = self.dependency_matches(&dep);
+
+                let hard = dep.negated().is_none() && dep.weakened().is_none();
+                if strict && hard && matches.is_empty() {
+                    return Err(Error::dangling_dependency(&pkg.id(), &dep));
+                }
+
+                // Edges in the order the dependencies list them
+                for m in matches.iter() {
+                    let target = graph.add_node(m.id());
+                    graph.add_edge(source, target, dep.clone())?;
+                }
+                pending.extend(matches);
             }
         }
 
-        // Add edges
-        let edges = nodes
-            .keys()
-            .flat_map(|source| {
-                source
-                    .dependencies()
-                    .into_iter()
-                    .flat_map(|dep| self.dependency_matches(&dep))
-                    .map(move |dep| (source, dep))
-            });
+        Ok(graph)
+    }
+
+    /// Packages selected for `root_id`, like `solve`, failing on a missing
+    /// root, on a dependency `to_node` follows that matches nothing, and on
+    /// a lack of solution.
+    pub fn try_solve(&self, root_id: &P::Id) -> Result<Vec<P::Id>, Error<P::Id>> {
+        self.try_solve_within(root_id, Budget::new())
+    }
+
+    /// Like `try_solve`, failing with `Error::BudgetExceeded` once `budget`
+    /// is exceeded.
+    pub fn try_solve_within(&self, root_id: &P::Id, budget: Budget) -> Result<Vec<P::Id>, Error<P::Id>> {
+        let root = self.get(root_id).ok_or_else(|| Error::unknown_package(root_id))?;
+        self.check_followed(root)?;
+
+        let node = self.to_node(root_id).ok_or_else(|| Error::unknown_package(root_id))?;
+        let mut context = Context::first_solution().bounded(budget);
+        let resolved = node.solve_within(Path::new(vec![]), &mut context).into_result()?;
+
+        resolved.paths
+            .first()
+            .map(|path| path.idents())
+            .ok_or_else(|| Error::no_solution(root_id))
+    }
+
+    /// Fails on a dependency `to_node` follows from `root` that matches
+    /// nothing.
+    fn check_followed(&self, root: &P) -> Result<(), Error<P::Id>> {
+        let mut visited = HashSet::new();
+        let mut pending = vec![root];
+
+        while let Some(pkg) = pending.pop() {
+            if !visited.insert(pkg.id()) {
+                continue;
+            }
 
-        for (source, target) in edges {
-            let a = nodes.get(source).unwrap();
-            let b = nodes.get(target).unwrap();
-            a.borrow_mut().add_dependency(b);
+            for dep in pkg.dependencies().iter().filter(|dep| Self::followed(dep)) {
+                let matches = self.dependency_matches(dep);
+                if matches.is_empty() {
+                    return Err(Error::dangling_dependency(&pkg.id(), dep));
+                }
+                pending.extend(matches);
+            }
         }
-        
-        nodes.get(&root).unwrap().clone()
+
+        Ok(())
     }
 
-    fn get(&self, id: &P::Id) -> Option<&P> {
-        self.packages.get(id)
+    /// Checks if `to_node` follows `dep`: a hard dependency of the normal
+    /// scope.
+    fn followed(dep: &Dependency<P::Id>) -> bool {
+        dep.negated().is_none() && dep.weakened().is_none() && dep.scope() == Scope::Normal
     }
 
-    fn dependency_matches(&self, dep: &Dependency<P::Id>) -> Vec<&P> {
-        dep
-            .ids()
+    /// Packages selected for `root_id` by the recursive solver, in the
+    /// order of the first solution, root first. `None` if the root is not in
+    /// the repository, or if there is no solution.
+    ///
+    /// Follows the dependencies `to_node` does.
+    pub fn solve(&self, root_id: &P::Id) -> Option<Vec<P::Id>> {
+        let node = self.to_node(root_id)?;
+        let resolved = node.solve_in(Path::new(vec![]), &mut Context::first_solution());
+
+        resolved.paths
+            .first()
+            .map(|path| path.idents())
+    }
+
+    /// Nodes standing for `root_id` and the packages it depends on, `None`
+    /// if the root is not in the repository.
+    ///
+    /// Only the hard dependencies of the normal scope are followed, with
+    /// markers and features left out, as only the SAT backend understands
+    /// them. A package depending on one of its ancestors gets a node
+    /// without dependencies for it, which conflicts with the ancestor unless
+    /// the context allows cycles. Other packages get a single node, shared
+    /// by all the packages depending on them.
+    pub fn to_node(&self, root_id: &P::Id) -> Option<Node<Requirements<P::Id>>> {
+        let root = self.get(root_id)?;
+        let (node, _) = self.node(root, &mut vec![], &mut HashMap::new());
+        Some((*node).clone())
+    }
+
+    /// Node of `pkg`, whose ancestors are `ancestors`, along with the depth
+    /// of the outermost ancestor its subtree cuts a cycle at, if any.
+    ///
+    /// Nodes not cutting cycles at their ancestors are the same wherever
+    /// they appear, so they are only built once and kept in `built`.
+    fn node(&self, pkg: &P, ancestors: &mut Vec<P::Id>, built: &mut HashMap<P::Id, Shared<P::Id>>) -> (Shared<P::Id>, Option<usize>) {
+        let id = pkg.id();
+        if let Some(depth) = ancestors.iter().position(|ancestor| *ancestor == id) {
+            return (Arc::new(Node { id, dependency: None }), Some(depth));
+        }
+        if let Some(node) = built.get(&id) {
+            return (node.clone(), None);
+        }
+
+        let dependencies = pkg.dependencies();
+        let exclusions = dependencies
             .iter()
+            .flat_map(|dep| self.excluded(dep))
+            .map(|other| other.id())
+            .collect();
+
+        let depth = ancestors.len();
+        let mut cut = None;
+        let mut required = vec![];
+
+        ancestors.push(id.clone());
+        for dep in dependencies.iter() {
+            if !Self::followed(dep) {
+                continue;
+            }
+
+            let mut matches = vec![];
+            for m in self.dependency_matches(dep) {
+                let (node, at) = self.node(m, ancestors, built);
+                cut = cut.into_iter().chain(at).min();
+                matches.push(node);
+            }
+            required.push(matches);
+        }
+        ancestors.pop();
+
+        let node = Arc::new(Node {
+            id: id.clone(),
+            dependency: Some(Requirements { dependencies: required, exclusions })
+        });
+
+        // Cycles cut at the node itself don't depend on its ancestors
+        let cut = cut.filter(|&at| at < depth);
+        if cut.is_none() {
+            built.insert(id, node.clone());
+        }
+        (node, cut)
+    }
+
+    pub fn get(&self, id: &P::Id) -> Option<&P> {
+        self.packages.get(id)
+    }
+
+    /// Packages satisfying `dep`, if only weakly. Negative dependencies have
+    /// none, see `excluded`.
+    pub fn dependency_matches(&self, dep: &Dependency<P::Id>) -> Vec<&P> {
+        match dep {
+            Dependency::Choice(ids) => ids
+                .iter()
+                .filter_map(|id| self.get(id))
+                .collect(),
+            Dependency::Range(name, requirement) => self.versions_matching(name, |version| requirement.matches(version)),
+            Dependency::Virtual(name) => {
+                let mut matches = self.versions_matching(name, |_| true);
+
+                for provider in self.providers(name) {
+                    if !matches.contains(&provider) {
+                        matches.push(provider);
+                    }
+                }
+                matches
+            },
+            Dependency::Conflicts(_) | Dependency::Breaks(_) => vec![],
+            Dependency::WithFeatures(dep, _) | Dependency::When(dep, _) => self.dependency_matches(dep),
+            Dependency::Scoped(dep, _) => self.dependency_matches(dep),
+            Dependency::Recommends(dep) | Dependency::Suggests(dep) => self.dependency_matches(dep)
+        }
+    }
+
+    /// Packages providing the virtual package `name`, in the order they
+    /// were added.
+    pub fn providers(&self, name: &str) -> Vec<&P> {
+        self.providers
+            .get(name)
+            .into_iter()
+            .flatten()
+            .filter_map(|id| self.get(id))
+            .collect()
+    }
+
+    /// Packages a negative dependency rules out, none for other ones.
+    pub fn excluded(&self, dep: &Dependency<P::Id>) -> Vec<&P> {
+        match dep.negated() {
+            Some(negated) => self.dependency_matches(negated),
+            None => vec![]
+        }
+    }
+
+    /// Versions of the package `name` accepted by `filter`, newest first.
+    fn versions_matching<F: Fn(&Version) -> bool>(&self, name: &str, filter: F) -> Vec<&P> {
+        let mut matches: Vec<_> = self.versions
+            .get(name)
+            .into_iter()
+            .flatten()
+            .filter(|id| match id.version() {
+                Some(version) => filter(version),
+                None => false
+            })
+            .collect();
+
+        matches.sort_by(|a, b| b.version().cmp(&a.version()));
+        matches
+            .into_iter()
             .filter_map(|id| self.get(id))
             .collect()
     }
@@ -87,9 +360,13 @@ impl<P: Package> Repository<P> {
 
 #[cfg(test)]
 mod tests {
-    use node::Node;
-    use node::resolvable::Resolvable;
+    use budget::Reason;
+    use context::Context;
+    use package::graph::NodeIndex;
     use package::ident::SimpleUnique;
+    use package::ident::Versioned;
+    use package::marker::Marker;
+    use package::requirement::Requirement;
     use super::*;
 
     #[derive(Clone, Debug, Eq, Hash, PartialEq)]
@@ -108,17 +385,13 @@ mod tests {
         fn dependencies(&self) -> Vec<Dependency<Self::Id>> {
             self.dependencies.clone().into_iter().collect()
         }
-
-        fn to_node<R: Resolvable>(&self) -> Node<R> {
-            unimplemented!();
-        }
     }
 
     #[test]
     fn finds_dependency_matches() {
 
         let ids: Vec<_> =
-            vec!["a", "b", "c", "d"]
+            ["a", "b", "c", "d"]
             .iter()
             .map(|id| SimpleUnique { id })
             .collect();
@@ -146,11 +419,129 @@ mod tests {
             overlapping_pkgs.iter().collect::<Vec<_>>());
     }
 
+    #[derive(Clone, Debug, Eq, Hash, PartialEq)]
+    struct MockVersioned {
+        id: Versioned,
+        provides: Vec<String>
+    }
+
+    impl Package for MockVersioned {
+        type Id = Versioned;
+
+        fn id(&self) -> Self::Id {
+            self.id.clone()
+        }
+
+        fn dependencies(&self) -> Vec<Dependency<Self::Id>> {
+            vec![]
+        }
+
+        fn provides(&self) -> Vec<String> {
+            self.provides.clone()
+        }
+    }
+
+    #[test]
+    fn expands_ranges() {
+        let versioned = |name, version: &str| Versioned::new(name, version.parse().unwrap());
+
+        let repo = Repository::new(
+            [("a", "1.1.0"), ("a", "1.5.0"), ("a", "1.3.0"), ("a", "2.0.0"), ("a", "1.4.0-rc.1"), ("b", "1.3.0")]
+                .iter()
+                .map(|&(name, version)| MockVersioned { id: versioned(name, version), provides: vec![] }));
+
+        let matches = |requirement: &str| -> Vec<String> {
+            let dep = Dependency::range("a", requirement.parse().unwrap());
+            repo.dependency_matches(&dep)
+                .into_iter()
+                .map(|pkg| pkg.id.to_string())
+                .collect()
+        };
+
+        assert_eq!(matches("^1.2"), vec!["a 1.5.0", "a 1.3.0"]);
+        assert_eq!(matches("~1.1"), vec!["a 1.1.0"]);
+        assert_eq!(matches("*"), vec!["a 2.0.0", "a 1.5.0", "a 1.3.0", "a 1.1.0"]);
+        assert_eq!(matches(">=3"), Vec::<String>::new());
+
+        let unknown = Dependency::range("c", Requirement::any());
+        assert!(repo.dependency_matches(&unknown).is_empty());
+    }
+
+    #[test]
+    fn finds_excluded() {
+        let versioned = |version: &str| Versioned::new("a", version.parse().unwrap());
+
+        let repo = Repository::new(
+            ["1.1.0", "1.5.0", "2.0.0"]
+                .iter()
+                .map(|version| MockVersioned { id: versioned(version), provides: vec![] }));
+
+        let range = Dependency::range("a", "<1.5".parse().unwrap());
+        let conflicts = Dependency::conflicts(range.clone());
+        let breaks = Dependency::breaks(Dependency::Choice(vec![versioned("2.0.0"), versioned("3.0.0")]));
+
+        assert!(repo.dependency_matches(&conflicts).is_empty());
+        assert!(repo.excluded(&range).is_empty());
+        assert_eq!(repo.excluded(&conflicts), repo.dependency_matches(&range));
+        assert_eq!(
+            repo.excluded(&breaks),
+            vec![&MockVersioned { id: versioned("2.0.0"), provides: vec![] }]);
+    }
+
+    #[test]
+    fn resolves_virtual_packages() {
+        let pkg = |name, version: &str, provides: &[&str]| MockVersioned {
+            id: Versioned::new(name, version.parse().unwrap()),
+            provides: provides.iter().map(|name| name.to_string()).collect()
+        };
+
+        let repo = Repository::new(vec![
+            pkg("postfix", "3.0.0", &["mta"]),
+            pkg("mta", "1.0.0", &[]),
+            pkg("exim", "4.0.0", &["mta", "mail-reader"]),
+            pkg("mta", "2.0.0", &["mta"])]);
+
+        let ids = |pkgs: Vec<&MockVersioned>| -> Vec<String> {
+            pkgs.into_iter().map(|pkg| pkg.id.to_string()).collect()
+        };
+
+        assert_eq!(ids(repo.providers("mta")), vec!["postfix 3.0.0", "exim 4.0.0", "mta 2.0.0"]);
+        assert_eq!(ids(repo.providers("mail-reader")), vec!["exim 4.0.0"]);
+        assert!(repo.providers("editor").is_empty());
+
+        assert_eq!(
+            ids(repo.dependency_matches(&Dependency::provider("mta"))),
+            vec!["mta 2.0.0", "mta 1.0.0", "postfix 3.0.0", "exim 4.0.0"]);
+        assert_eq!(
+            ids(repo.excluded(&Dependency::exclusive("mail-reader"))),
+            vec!["exim 4.0.0"]);
+    }
+
+    #[test]
+    fn replaces_providers() {
+        let pkg = |provides: &[&str]| MockVersioned {
+            id: Versioned::new("exim", "4.0.0".parse().unwrap()),
+            provides: provides.iter().map(|name| name.to_string()).collect()
+        };
+
+        let mut repo = Repository::new(vec![pkg(&["mta", "mail-reader"])]);
+        repo.add(pkg(&["mta", "smtp"]));
+
+        let ids = |pkgs: Vec<&MockVersioned>| -> Vec<String> {
+            pkgs.into_iter().map(|pkg| pkg.id.to_string()).collect()
+        };
+
+        assert_eq!(ids(repo.providers("mta")), vec!["exim 4.0.0"]);
+        assert_eq!(ids(repo.providers("smtp")), vec!["exim 4.0.0"]);
+        assert!(repo.providers("mail-reader").is_empty());
+        assert_eq!(ids(repo.dependency_matches(&Dependency::provider("exim"))), vec!["exim 4.0.0"]);
+    }
+
     #[test]
     fn builds_graph() {
 
         let ids: Vec<_> =
-            vec!["a", "b", "c"]
+            ["a", "b", "c"]
             .iter()
             .map(|id| SimpleUnique { id })
             .collect();
@@ -176,21 +567,281 @@ mod tests {
             dependencies: vec![]
         };
 
-        let raws: Vec<_> = ids
-            .iter()
-            .map(|id| RawNode::new(id.clone()))
-            .collect();
-
-        let (a_r, b_r, c_r) = (raws[0].clone(), raws[1].clone(), raws[2].clone());
-        a_r.borrow_mut().add_dependency(&b_r);
-        a_r.borrow_mut().add_dependency(&c_r);
-        b_r.borrow_mut().add_dependency(&c_r);
+        let mut expected = Graph::new();
+        let (a_n, b_n, c_n) = (expected.add_node(a.clone()), expected.add_node(b.clone()), expected.add_node(c.clone()));
+        expected.add_edge(a_n, b_n, Dependency::Choice(vec![b.clone()])).unwrap();
+        expected.add_edge(a_n, c_n, Dependency::Choice(vec![c.clone()])).unwrap();
+        expected.add_edge(b_n, c_n, Dependency::Choice(vec![c.clone()])).unwrap();
 
         let repo = Repository::new(vec![a_, b_, c_]);
-        let root_node = repo.build_graph(&root);
+        let graph = repo.build_graph(&root);
 
         assert_eq!(
-            root_node,
-            a_r);
+            graph,
+            expected);
+    }
+
+    #[test]
+    fn builds_cyclic_graph() {
+        let (a, b) = (SimpleUnique::new("a"), SimpleUnique::new("b"));
+        let repo = Repository::new(vec![
+            MockPackage { id: a.clone(), dependencies: vec![Dependency::Choice(vec![b.clone()])] },
+            MockPackage { id: b.clone(), dependencies: vec![Dependency::Choice(vec![a.clone()])] }]);
+
+        let graph = repo.build_graph(&a);
+        let root = graph.index(&a).unwrap();
+
+        assert_eq!(graph.len(), 2);
+        assert_eq!(graph.find_cycle(root).map(|cycle| cycle.len()), Some(3));
+        assert_eq!(graph, repo.build_graph(&a));
+    }
+
+    #[test]
+    fn builds_graph_in_environment() {
+        let (a, b, c) = (SimpleUnique::new("a"), SimpleUnique::new("b"), SimpleUnique::new("c"));
+        let windows: Marker = "os == 'windows'".parse().unwrap();
+
+        let repo = Repository::new(vec![
+            MockPackage {
+                id: a.clone(),
+                dependencies: vec![
+                    Dependency::Choice(vec![b.clone()]),
+                    Dependency::Choice(vec![c.clone()]).when(windows.clone())]
+            },
+            MockPackage { id: b.clone(), dependencies: vec![] },
+            MockPackage { id: c.clone(), dependencies: vec![] }]);
+
+        let deps = |graph: Graph<SimpleUnique>| -> Vec<SimpleUnique> {
+            graph
+                .dependencies(NodeIndex(0))
+                .into_iter()
+                .map(|edge| graph.id(edge.target).unwrap().clone())
+                .collect()
+        };
+
+        let linux = Environment::new().with("os", "linux");
+        assert_eq!(deps(repo.build_graph_in(&a, &linux)), vec![b.clone()]);
+        assert_eq!(deps(repo.build_graph_in(&a, &Environment::new().with("os", "windows"))), vec![b.clone(), c.clone()]);
+        assert_eq!(deps(repo.build_graph(&a)), vec![b, c]);
+    }
+
+    #[test]
+    fn solves_through_nodes() {
+        // a -> b, a -> c, b -> d, c -> d | e, x -> b | e, x -> f, f
+        // conflicts with d, p -> q, q -> p
+        let choice = |ids: &[&'static str]| Dependency::Choice(ids.iter().map(|&id| SimpleUnique::new(id)).collect());
+        let pkg = |id, dependencies| MockPackage { id: SimpleUnique::new(id), dependencies };
+
+        let repo = Repository::new(vec![
+            pkg("a", vec![choice(&["b"]), choice(&["c"])]),
+            pkg("b", vec![choice(&["d"])]),
+            pkg("c", vec![choice(&["d", "e"])]),
+            pkg("d", vec![]),
+            pkg("e", vec![]),
+            pkg("x", vec![choice(&["b", "e"]), choice(&["f"])]),
+            pkg("f", vec![Dependency::conflicts(choice(&["d"]))]),
+            pkg("p", vec![choice(&["q"])]),
+            pkg("q", vec![choice(&["p"])])]);
+
+        let solve = |id| repo
+            .solve(&SimpleUnique::new(id))
+            .map(|ids| ids.into_iter().map(|id| id.id).collect::<Vec<_>>());
+
+        assert_eq!(solve("a"), Some(vec!["a", "b", "d", "c"]));
+        assert_eq!(solve("x"), Some(vec!["x", "e", "f"]));
+        assert_eq!(solve("p"), None);
+        assert_eq!(solve("z"), None);
+
+        let p = repo.to_node(&SimpleUnique::new("p")).unwrap();
+        let mut context = Context::new().allowing_cycles();
+        let res = p.solve_in(Path::new(vec![]), &mut context);
+
+        let ids: Vec<Vec<_>> = res.paths.iter().map(|path| path.idents().into_iter().map(|id| id.id).collect()).collect();
+        assert_eq!(ids, vec![vec!["p", "q"]]);
+        let cycle = vec![SimpleUnique::new("p"), SimpleUnique::new("q"), SimpleUnique::new("p")];
+        assert_eq!(res.paths[0].cycles, vec![cycle.clone()]);
+        assert_eq!(context.cycles(), &[cycle][..]);
+    }
+
+    #[test]
+    fn keeps_cycles_of_cached_nodes() {
+        // r -> x | x2, x -> y, x2 -> y, y -> s, s -> r
+        let choice = |ids: &[&'static str]| Dependency::Choice(ids.iter().map(|&id| SimpleUnique::new(id)).collect());
+        let pkg = |id, dependencies| MockPackage { id: SimpleUnique::new(id), dependencies };
+        let ids = |names: &[&'static str]| names.iter().map(|&name| SimpleUnique::new(name)).collect::<Vec<_>>();
+
+        let repo = Repository::new(vec![
+            pkg("r", vec![choice(&["x", "x2"])]),
+            pkg("x", vec![choice(&["y"])]),
+            pkg("x2", vec![choice(&["y"])]),
+            pkg("y", vec![choice(&["s"])]),
+            pkg("s", vec![choice(&["r"])])]);
+        let r = repo.to_node(&SimpleUnique::new("r")).unwrap();
+
+        let res = r.solve_in(Path::new(vec![]), &mut Context::new().allowing_cycles());
+        let cycles: Vec<_> = res.paths.iter().map(|path| path.cycles.clone()).collect();
+        assert_eq!(cycles, vec![vec![ids(&["r", "x", "y", "s", "r"])], vec![ids(&["r", "x2", "y", "s", "r"])]]);
+
+        let iterative = r.solve_iteratively(Path::new(vec![]), &mut Context::new().allowing_cycles());
+        assert_eq!(iterative, res);
+
+        let lazy: Vec<_> = r.solutions(Path::new(vec![])).allowing_cycles().collect();
+        assert_eq!(lazy, res.paths);
+    }
+
+    /// Chain of `length` diamonds, p0 -> a0, p0 -> b0, a0 -> p1, b0 -> p1 and
+    /// so on, the last p depending on x1 | x2.
+    fn diamonds(length: usize) -> Repository<MockPackage> {
+        let name = |prefix, i: usize| SimpleUnique::new(Box::leak(format!("{}{}", prefix, i).into_boxed_str()));
+        let pkg = |id, dependencies| MockPackage { id, dependencies };
+
+        let mut pkgs = vec![
+            pkg(name("p", length), vec![Dependency::Choice(vec![SimpleUnique::new("x1"), SimpleUnique::new("x2")])]),
+            pkg(SimpleUnique::new("x1"), vec![]),
+            pkg(SimpleUnique::new("x2"), vec![])];
+
+        for i in 0..length {
+            let next = Dependency::Choice(vec![name("p", i + 1)]);
+            pkgs.push(pkg(name("p", i), vec![Dependency::Choice(vec![name("a", i)]), Dependency::Choice(vec![name("b", i)])]));
+            pkgs.push(pkg(name("a", i), vec![next.clone()]));
+            pkgs.push(pkg(name("b", i), vec![next]));
+        }
+
+        Repository::new(pkgs)
+    }
+
+    #[test]
+    fn shares_nodes_between_dependents() {
+        let repo = diamonds(40);
+        let p0 = repo.to_node(&SimpleUnique::new("p0")).unwrap();
+
+        let res = p0.solve(Path::new(vec![]));
+        let ids: Vec<_> = res.paths.iter().map(|path| path.idents()).collect();
+        assert_eq!(ids.len(), 2);
+        assert!(ids[0].contains(&SimpleUnique::new("x1")) && ids[1].contains(&SimpleUnique::new("x2")));
+        assert_eq!(ids[0].len(), 3 * 40 + 2);
+
+        let lazy: Vec<_> = p0.solutions(Path::new(vec![])).collect();
+        assert_eq!(lazy, res.paths);
+    }
+
+    #[test]
+    fn solves_shared_nodes_alike() {
+        let repo = diamonds(4);
+        let p0 = repo.to_node(&SimpleUnique::new("p0")).unwrap();
+
+        let res = p0.solve(Path::new(vec![]));
+        assert_eq!(res.paths.len(), 2);
+
+        let iterative = p0.solve_iteratively(Path::new(vec![]), &mut Context::new());
+        assert_eq!(iterative.paths, res.paths);
+
+        let lazy: Vec<_> = p0.solutions(Path::new(vec![])).collect();
+        assert_eq!(lazy, res.paths);
+
+        let first = p0.solve_in(Path::new(vec![]), &mut Context::first_solution());
+        assert_eq!(first.paths, res.paths[..1].to_vec());
+
+        let parallel = p0.solve_parallel(Path::new(vec![]), &mut Context::new(), 2);
+        assert_eq!(parallel.paths, res.paths);
+    }
+
+    #[test]
+    fn solves_shared_nodes_once() {
+        // a -> b & c, b -> d, c -> d, d -> e | f
+        let ids = |names: &[&'static str]| names.iter().map(|&name| SimpleUnique::new(name)).collect::<Vec<_>>();
+        let pkg = |id, dependencies: Vec<Vec<&'static str>>| MockPackage {
+            id: SimpleUnique::new(id),
+            dependencies: dependencies.iter().map(|names| Dependency::Choice(ids(names))).collect()
+        };
+        let repo = Repository::new(vec![
+            pkg("a", vec![vec!["b"], vec!["c"]]),
+            pkg("b", vec![vec!["d"]]),
+            pkg("c", vec![vec!["d"]]),
+            pkg("d", vec![vec!["e", "f"]]),
+            pkg("e", vec![]),
+            pkg("f", vec![])]);
+        let a = repo.to_node(&SimpleUnique::new("a")).unwrap();
+
+        let res = a.solve(Path::new(vec![]));
+        let found: Vec<_> = res.paths.iter().map(|path| path.idents()).collect();
+        assert_eq!(found, vec![ids(&["a", "b", "d", "e", "c"]), ids(&["a", "b", "d", "f", "c"])]);
+
+        let iterative = a.solve_iteratively(Path::new(vec![]), &mut Context::new());
+        assert_eq!(iterative, res);
+
+        let lazy: Vec<_> = a.solutions(Path::new(vec![])).collect();
+        assert_eq!(lazy, res.paths);
+
+        let parallel = a.solve_parallel(Path::new(vec![]), &mut Context::new(), 2);
+        assert_eq!(parallel.paths, res.paths);
+    }
+
+    #[test]
+    fn solves_for_the_first_solution_only() {
+        // root -> c0 & ... & c29, ci -> xi | yi, 2^30 solutions in all
+        let name = |prefix, i: usize| SimpleUnique::new(Box::leak(format!("{}{}", prefix, i).into_boxed_str()));
+        let choice = |ids: Vec<SimpleUnique>| Dependency::Choice(ids);
+
+        let mut pkgs = vec![MockPackage {
+            id: SimpleUnique::new("root"),
+            dependencies: (0..30).map(|i| choice(vec![name("c", i)])).collect()
+        }];
+        for i in 0..30 {
+            pkgs.push(MockPackage { id: name("c", i), dependencies: vec![choice(vec![name("x", i), name("y", i)])] });
+            pkgs.push(MockPackage { id: name("x", i), dependencies: vec![] });
+            pkgs.push(MockPackage { id: name("y", i), dependencies: vec![] });
+        }
+        let repo = Repository::new(pkgs);
+
+        let solution = repo.solve(&SimpleUnique::new("root")).unwrap();
+        assert_eq!(solution.len(), 61);
+        assert!(solution.contains(&name("x", 29)) && !solution.contains(&name("y", 29)));
+    }
+
+    #[test]
+    fn reports_errors() {
+        let (a, b, c) = (SimpleUnique::new("a"), SimpleUnique::new("b"), SimpleUnique::new("c"));
+        let repo = Repository::new(vec![
+            MockPackage {
+                id: a.clone(),
+                dependencies: vec![
+                    Dependency::Choice(vec![b.clone()]),
+                    Dependency::recommends(Dependency::Choice(vec![c.clone()]))]
+            },
+            MockPackage { id: b.clone(), dependencies: vec![Dependency::Choice(vec![c.clone()])] }]);
+
+        assert_eq!(repo.try_build_graph(&c, None), Err(Error::unknown_package(&c)));
+        assert_eq!(
+            repo.try_build_graph(&a, None),
+            Err(Error::dangling_dependency(&b, &Dependency::Choice(vec![c.clone()]))));
+        assert_eq!(repo.try_solve(&c), Err(Error::unknown_package(&c)));
+        assert_eq!(
+            repo.try_solve(&a),
+            Err(Error::dangling_dependency(&b, &Dependency::Choice(vec![c.clone()]))));
+
+        // Dependencies `to_node` doesn't follow may match nothing
+        let repo = Repository::new(vec![MockPackage {
+            id: a.clone(),
+            dependencies: vec![
+                Dependency::Choice(vec![c.clone()]).in_scope(Scope::Dev),
+                Dependency::recommends(Dependency::Choice(vec![c.clone()]))]
+        }]);
+        assert_eq!(repo.try_solve(&a), Ok(vec![a.clone()]));
+
+        let repo = Repository::new(vec![
+            MockPackage { id: a.clone(), dependencies: vec![Dependency::Choice(vec![b.clone()])] },
+            MockPackage { id: b.clone(), dependencies: vec![Dependency::conflicts(Dependency::Choice(vec![a.clone()]))] }]);
+        assert_eq!(repo.try_solve(&a), Err(Error::no_solution(&a)));
+        match repo.try_solve_within(&a, Budget::new().steps(1)) {
+            Err(Error::BudgetExceeded(aborted)) => assert_eq!(aborted.reason, Reason::Steps),
+            other => panic!("expected the budget to run out, got {:?}", other)
+        }
+
+        let repo = Repository::new(vec![MockPackage { id: b.clone(), dependencies: vec![] }]);
+        let mut graph = Graph::new();
+        graph.add_node(b.clone());
+        assert_eq!(repo.try_build_graph(&b, None), Ok(graph));
+        assert_eq!(repo.try_solve(&b), Ok(vec![b]));
     }
 }
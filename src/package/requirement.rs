@@ -0,0 +1,308 @@
+use std::fmt;
+use std::str::FromStr;
+
+use package::version::Identifier;
+use package::version::ParseError;
+use package::version::Version;
+use package::version::numeric;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Op {
+    Exact,
+    Greater,
+    GreaterEq,
+    Less,
+    LessEq,
+    /// Same minor version, or major if that's all there is
+    Tilde,
+    /// No change to the leftmost non-zero part
+    Caret,
+    /// Any value of the missing parts
+    Wildcard
+}
+
+/// Operator applied to a version whose minor and patch may be left out.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Comparator {
+    pub op: Op,
+    pub major: u64,
+    pub minor: Option<u64>,
+    pub patch: Option<u64>,
+    pub pre: Vec<Identifier>
+}
+
+/// Version bounding a range, and whether it is part of it.
+type Bound = Option<(Version, bool)>;
+
+impl Comparator {
+    pub fn matches(&self, version: &Version) -> bool {
+        let (lower, upper) = self.bounds();
+
+        let above = match lower {
+            Some((ref bound, inclusive)) => version > bound || (inclusive && version == bound),
+            None => true
+        };
+        let below = match upper {
+            Some((ref bound, inclusive)) => version < bound || (inclusive && version == bound),
+            None => true
+        };
+
+        above && below
+    }
+
+    /// Lower and upper bounds, and whether they are inclusive.
+    fn bounds(&self) -> (Bound, Bound) {
+        let (major, minor, patch) = (self.major, self.minor, self.patch);
+        let lowest = Version {
+            pre: self.pre.clone(),
+            ..Version::new(major, minor.unwrap_or(0), patch.unwrap_or(0))
+        };
+
+        // First version past the parts that are given
+        let next = match (minor, patch) {
+            (Some(minor), Some(patch)) => Version::new(major, minor, patch + 1),
+            (Some(minor), None) => Version::new(major, minor + 1, 0),
+            _ => Version::new(major + 1, 0, 0)
+        };
+        let exact = patch.is_some();
+
+        match self.op {
+            Op::Exact if exact => (Some((lowest.clone(), true)), Some((lowest, true))),
+            Op::Exact | Op::Wildcard => (Some((lowest, true)), Some((next, false))),
+            Op::Greater if exact => (Some((lowest, false)), None),
+            Op::Greater => (Some((next, true)), None),
+            Op::GreaterEq => (Some((lowest, true)), None),
+            Op::Less => (None, Some((lowest, false))),
+            Op::LessEq if exact => (None, Some((lowest, true))),
+            Op::LessEq => (None, Some((next, false))),
+            Op::Tilde => {
+                let upper = match minor {
+                    Some(minor) => Version::new(major, minor + 1, 0),
+                    None => Version::new(major + 1, 0, 0)
+                };
+                (Some((lowest, true)), Some((upper, false)))
+            },
+            Op::Caret => {
+                let upper = match (major, minor, patch) {
+                    (0, Some(0), Some(patch)) => Version::new(0, 0, patch + 1),
+                    (0, Some(minor), _) => Version::new(0, minor + 1, 0),
+                    _ => Version::new(major + 1, 0, 0)
+                };
+                (Some((lowest, true)), Some((upper, false)))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Comparator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let op = match self.op {
+            Op::Exact => "=",
+            Op::Greater => ">",
+            Op::GreaterEq => ">=",
+            Op::Less => "<",
+            Op::LessEq => "<=",
+            Op::Tilde => "~",
+            Op::Caret => "^",
+            Op::Wildcard => ""
+        };
+        write!(f, "{}{}", op, self.major)?;
+
+        let wildcard = if let Op::Wildcard = self.op { ".*" } else { "" };
+        match (self.minor, self.patch) {
+            (Some(minor), Some(patch)) => write!(f, ".{}.{}", minor, patch)?,
+            (Some(minor), None) => write!(f, ".{}{}", minor, wildcard)?,
+            _ => write!(f, "{}", wildcard)?
+        }
+
+        if !self.pre.is_empty() {
+            let pre: Vec<_> = self.pre.iter().map(|id| id.to_string()).collect();
+            write!(f, "-{}", pre.join("."))?;
+        }
+        Ok(())
+    }
+}
+
+/// Versions satisfying all of its comparators, Cargo style: `>=1.2, <2`,
+/// `^1.2.3` (also written `1.2.3`), `~1.2`, `1.*`, or `*` for any.
+///
+/// Pre-releases only match if a comparator names a pre-release of the
+/// same major, minor and patch.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Requirement {
+    pub comparators: Vec<Comparator>
+}
+
+impl Requirement {
+    /// Requirement matching any release.
+    pub fn any() -> Self {
+        Requirement { comparators: vec![] }
+    }
+
+    pub fn parse(input: &str) -> Result<Self, ParseError> {
+        if input.trim() == "*" {
+            return Ok(Self::any());
+        }
+
+        let comparators = input
+            .split(',')
+            .map(|part| parse_comparator(part.trim()).ok_or_else(|| ParseError::new(input, "invalid requirement")))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Requirement { comparators })
+    }
+
+    pub fn matches(&self, version: &Version) -> bool {
+        let allowed_pre = !version.is_prerelease() || self.comparators
+            .iter()
+            .any(|c| !c.pre.is_empty()
+                && c.major == version.major
+                && c.minor == Some(version.minor)
+                && c.patch == Some(version.patch));
+
+        allowed_pre && self.comparators.iter().all(|c| c.matches(version))
+    }
+}
+
+impl FromStr for Requirement {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, ParseError> {
+        Requirement::parse(s)
+    }
+}
+
+impl fmt::Display for Requirement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.comparators.is_empty() {
+            return write!(f, "*");
+        }
+
+        let comparators: Vec<_> = self.comparators.iter().map(|c| c.to_string()).collect();
+        write!(f, "{}", comparators.join(", "))
+    }
+}
+
+fn parse_comparator(input: &str) -> Option<Comparator> {
+    let (op, rest) = [(">=", Op::GreaterEq), ("<=", Op::LessEq), (">", Op::Greater), ("<", Op::Less),
+                      ("=", Op::Exact), ("~", Op::Tilde), ("^", Op::Caret)]
+        .iter()
+        .find(|&&(prefix, _)| input.starts_with(prefix))
+        .map(|&(prefix, op)| (Some(op), input[prefix.len()..].trim_start()))
+        .unwrap_or((None, input));
+
+    let (core, pre) = match rest.find('-') {
+        Some(i) => (&rest[..i], Some(&rest[i + 1..])),
+        None => (rest, None)
+    };
+
+    // Numbers up to the first wildcard
+    let mut parts = vec![];
+    let mut wildcard = false;
+    for part in core.split('.') {
+        match part {
+            "*" | "x" | "X" => wildcard = true,
+            _ if wildcard => return None,
+            _ => parts.push(numeric(part)?)
+        }
+    }
+
+    let (major, minor, patch) = match parts[..] {
+        [major] => (major, None, None),
+        [major, minor] => (major, Some(minor), None),
+        [major, minor, patch] => (major, Some(minor), Some(patch)),
+        _ => return None
+    };
+    if core.split('.').count() > 3 {
+        return None;
+    }
+
+    // Pre-releases need a full version to refer to
+    let pre = match pre {
+        Some(pre) if patch.is_some() => Version::parse(&format!("0.0.0-{}", pre)).ok()?.pre,
+        Some(_) => return None,
+        None => vec![]
+    };
+
+    let op = match op {
+        Some(op) => op,
+        None if wildcard => Op::Wildcard,
+        None => Op::Caret
+    };
+
+    Some(Comparator { op, major, minor, patch, pre })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(requirement: &str, version: &str) -> bool {
+        let requirement: Requirement = requirement.parse().unwrap();
+        requirement.matches(&version.parse().unwrap())
+    }
+
+    #[test]
+    fn matches_comparators() {
+        assert!(matches(">=1.2, <2.0", "1.2.0"));
+        assert!(matches(">=1.2, <2.0", "1.9.9"));
+        assert!(!matches(">=1.2, <2.0", "2.0.0"));
+        assert!(!matches(">=1.2, <2.0", "1.1.9"));
+
+        assert!(matches(">1.2", "1.3.0"));
+        assert!(!matches(">1.2", "1.2.5"));
+        assert!(matches("<=1.2", "1.2.5"));
+        assert!(!matches("<=1.2.3", "1.2.4"));
+        assert!(matches("=1.2", "1.2.7"));
+        assert!(!matches("=1.2.3", "1.2.4"));
+    }
+
+    #[test]
+    fn matches_caret() {
+        assert!(matches("^1.2.3", "1.9.0"));
+        assert!(!matches("^1.2.3", "1.2.2"));
+        assert!(!matches("^1.2.3", "2.0.0"));
+        assert!(matches("1.2.3", "1.4.0"));
+        assert!(matches("^0.2.3", "0.2.9"));
+        assert!(!matches("^0.2.3", "0.3.0"));
+        assert!(matches("^0.0.3", "0.0.3"));
+        assert!(!matches("^0.0.3", "0.0.4"));
+        assert!(!matches("^0.0", "0.1.0"));
+        assert!(matches("^0", "0.9.0"));
+    }
+
+    #[test]
+    fn matches_tilde_and_wildcards() {
+        assert!(matches("~1.2.3", "1.2.9"));
+        assert!(!matches("~1.2.3", "1.3.0"));
+        assert!(matches("~1", "1.9.0"));
+        assert!(matches("1.*", "1.5.0"));
+        assert!(!matches("1.*", "2.0.0"));
+        assert!(matches("1.2.x", "1.2.5"));
+        assert!(!matches("1.2.x", "1.3.0"));
+        assert!(matches("*", "3.0.0"));
+    }
+
+    #[test]
+    fn matches_prereleases_explicitly() {
+        assert!(!matches("^1.2.3", "2.0.0-alpha"));
+        assert!(!matches("*", "1.0.0-rc.1"));
+        assert!(matches(">=1.2.3-alpha", "1.2.3-beta"));
+        assert!(!matches(">=1.2.3-alpha", "1.2.4-beta"));
+        assert!(matches(">=1.2.3-alpha", "1.2.4"));
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        for input in ["", ">=", "1.2.3.4", "1.*.3", "^a", "1.2-alpha", ">=1.2, ", "01.2", ">=1.02.3"].iter() {
+            assert!(Requirement::parse(input).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn displays() {
+        for input in [">=1.2, <2", "^1.2.3", "~1.2", "1.*", "*", "=1.2.3-rc.1"].iter() {
+            assert_eq!(Requirement::parse(input).unwrap().to_string(), *input);
+        }
+    }
+}
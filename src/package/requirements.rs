@@ -0,0 +1,82 @@
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::sync::Arc;
+
+use context::Context;
+use node::Node;
+use node::resolvable::Resolvable;
+use node::resolved::Resolved;
+use package::ident::Ident;
+use path::Path;
+use utils::Selections;
+
+/// Dependencies of a package, as `Repository::to_node` builds them: each
+/// dependency needs one of its matches, in order of preference.
+///
+/// Packages are shared between the nodes depending on them, and a package
+/// selected through several dependencies is only kept once, see
+/// `Resolvable::shares_nodes`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Requirements<Id: Ident> {
+    pub dependencies: Vec<Vec<Arc<Node<Requirements<Id>>>>>,
+    /// Ids the package declares it can't be selected with
+    pub exclusions: Vec<Id>
+}
+
+impl<Id: Ident> Resolvable for Requirements<Id> {
+    type Id = Id;
+
+    fn resolve<'a>(&'a self, path: Path<'a, Self>, context: &mut Context<'a, Self>) -> Resolved<'a, Self> {
+        let wanted = context.wanted();
+        let results = self
+            .selections()
+            .map(|nodes| Node::solve_together(&nodes, path.clone(), wanted, context));
+
+        Resolved::merge_wanted(results, wanted)
+    }
+
+    fn alternatives(&self) -> Vec<Vec<&Node<Self>>> {
+        self.selections().collect()
+    }
+
+    fn exclusions(&self) -> Vec<Id> {
+        self.exclusions.clone()
+    }
+
+    fn shares_nodes() -> bool {
+        true
+    }
+
+    fn nodes(&self) -> Vec<&Node<Self>> {
+        self.dependencies
+            .iter()
+            .flatten()
+            .map(|node| &**node)
+            .collect()
+    }
+}
+
+impl<Id: Ident> Requirements<Id> {
+    /// Lazy `alternatives`: one match of each dependency.
+    fn selections(&self) -> Selections<&Node<Self>> {
+        Selections::new(
+            self.dependencies
+                .iter()
+                .map(|matches| matches.iter().map(|node| &**node).collect())
+                .collect())
+    }
+}
+
+/// Only hashes the ids of the matches, as shared nodes would otherwise be
+/// hashed once per path leading to them.
+impl<Id: Ident> Hash for Requirements<Id> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for matches in self.dependencies.iter() {
+            matches.len().hash(state);
+            for node in matches {
+                node.id.hash(state);
+            }
+        }
+        self.exclusions.hash(state);
+    }
+}
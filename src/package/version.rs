@@ -0,0 +1,253 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+/// Dot-separated part of a pre-release.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Identifier {
+    Numeric(u64),
+    Alphanumeric(String)
+}
+
+impl Ord for Identifier {
+    /// Numeric identifiers are compared numerically and come before
+    /// alphanumeric ones, which are compared in ASCII order.
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Identifier::Numeric(a), Identifier::Numeric(b)) => a.cmp(b),
+            (Identifier::Numeric(_), Identifier::Alphanumeric(_)) => Ordering::Less,
+            (Identifier::Alphanumeric(_), Identifier::Numeric(_)) => Ordering::Greater,
+            (Identifier::Alphanumeric(a), Identifier::Alphanumeric(b)) => a.cmp(b)
+        }
+    }
+}
+
+impl PartialOrd for Identifier {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Identifier::Numeric(n) => write!(f, "{}", n),
+            Identifier::Alphanumeric(s) => write!(f, "{}", s)
+        }
+    }
+}
+
+/// Semantic version, as specified by semver 2.0.0.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    pub pre: Vec<Identifier>,
+    pub build: Vec<String>
+}
+
+impl Version {
+    pub fn new(major: u64, minor: u64, patch: u64) -> Self {
+        Version {
+            major,
+            minor,
+            patch,
+            pre: vec![],
+            build: vec![]
+        }
+    }
+
+    pub fn parse(input: &str) -> Result<Self, ParseError> {
+        let malformed = |reason| ParseError::new(input, reason);
+
+        let (rest, build) = match input.find('+') {
+            Some(i) => (&input[..i], Some(&input[i + 1..])),
+            None => (input, None)
+        };
+        let (core, pre) = match rest.find('-') {
+            Some(i) => (&rest[..i], Some(&rest[i + 1..])),
+            None => (rest, None)
+        };
+
+        let numbers = core
+            .split('.')
+            .map(numeric)
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| malformed("invalid version number"))?;
+
+        let (major, minor, patch) = match numbers[..] {
+            [major, minor, patch] => (major, minor, patch),
+            _ => return Err(malformed("expected major.minor.patch"))
+        };
+
+        let pre = match pre {
+            Some(pre) => pre
+                .split('.')
+                .map(|part| match numeric(part) {
+                    Some(n) => Some(Identifier::Numeric(n)),
+                    None if is_identifier(part) && !part.chars().all(|c| c.is_ascii_digit()) => {
+                        Some(Identifier::Alphanumeric(part.to_string()))
+                    },
+                    None => None
+                })
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| malformed("invalid pre-release"))?,
+            None => vec![]
+        };
+
+        let build = match build {
+            Some(build) => build
+                .split('.')
+                .map(|part| match is_identifier(part) {
+                    true => Some(part.to_string()),
+                    false => None
+                })
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| malformed("invalid build metadata"))?,
+            None => vec![]
+        };
+
+        Ok(Version { major, minor, patch, pre, build })
+    }
+
+    pub fn is_prerelease(&self) -> bool {
+        !self.pre.is_empty()
+    }
+
+    /// Order of precedence, which ignores build metadata. A pre-release
+    /// comes before its release.
+    pub fn precedence(&self, other: &Self) -> Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (self.pre.is_empty(), other.pre.is_empty()) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => self.pre.cmp(&other.pre)
+            })
+    }
+}
+
+/// Precedence, then build metadata so as to agree with `Eq`.
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.precedence(other).then_with(|| self.build.cmp(&other.build))
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl FromStr for Version {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, ParseError> {
+        Version::parse(s)
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+
+        if !self.pre.is_empty() {
+            let pre: Vec<_> = self.pre.iter().map(|id| id.to_string()).collect();
+            write!(f, "-{}", pre.join("."))?;
+        }
+        if !self.build.is_empty() {
+            write!(f, "+{}", self.build.join("."))?;
+        }
+        Ok(())
+    }
+}
+
+/// Version string that doesn't follow semver.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseError {
+    pub input: String,
+    pub reason: &'static str
+}
+
+impl ParseError {
+    pub fn new(input: &str, reason: &'static str) -> Self {
+        ParseError {
+            input: input.to_string(),
+            reason
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {:?}", self.reason, self.input)
+    }
+}
+
+/// Number without leading zeros.
+pub(crate) fn numeric(part: &str) -> Option<u64> {
+    let digits = !part.is_empty() && part.chars().all(|c| c.is_ascii_digit());
+    let leading_zero = part.len() > 1 && part.starts_with('0');
+
+    match digits && !leading_zero {
+        true => part.parse().ok(),
+        false => None
+    }
+}
+
+fn is_identifier(part: &str) -> bool {
+    !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(s: &str) -> Version {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses() {
+        assert_eq!(v("1.2.3"), Version::new(1, 2, 3));
+
+        let version = v("1.0.0-alpha.1+build.5-x");
+        assert_eq!(version.pre, vec![Identifier::Alphanumeric("alpha".to_string()), Identifier::Numeric(1)]);
+        assert_eq!(version.build, vec!["build".to_string(), "5-x".to_string()]);
+        assert_eq!(version.to_string(), "1.0.0-alpha.1+build.5-x");
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        for input in ["", "1", "1.2", "1.2.3.4", "01.2.3", "1.2.x", "1.2.3-", "1.2.3-01", "1.2.3-a..b", "1.2.3+", "1.2.3+a_b"].iter() {
+            assert!(Version::parse(input).is_err(), "{}", input);
+        }
+
+        assert_eq!(
+            Version::parse("1.2").unwrap_err().to_string(),
+            "expected major.minor.patch: \"1.2\"");
+    }
+
+    #[test]
+    fn orders_by_precedence() {
+        let ordered = [
+            "1.0.0-alpha", "1.0.0-alpha.1", "1.0.0-alpha.beta", "1.0.0-beta", "1.0.0-beta.2",
+            "1.0.0-beta.11", "1.0.0-rc.1", "1.0.0", "1.0.1", "1.1.0", "2.0.0"];
+
+        for pair in ordered.windows(2) {
+            assert!(v(pair[0]) < v(pair[1]), "{} < {}", pair[0], pair[1]);
+        }
+    }
+
+    #[test]
+    fn ignores_build_for_precedence() {
+        let (a, b) = (v("1.0.0+a"), v("1.0.0+b"));
+
+        assert_eq!(a.precedence(&b), Ordering::Equal);
+        assert!(a < b);
+        assert_ne!(a, b);
+    }
+}
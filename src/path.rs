@@ -1,15 +1,21 @@
+use std::ptr;
+
+use error::Error;
 use node::resolvable::Resolvable;
 use node::Node;
 use package::ident::Ident;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Path<'a, T: 'a + Resolvable> {
-    pub nodes: Vec<&'a Node<T>>
+    pub nodes: Vec<&'a Node<T>>,
+    /// Cycles the path closes, each from the node re-entered back to
+    /// itself, when the context allows them
+    pub cycles: Vec<Vec<T::Id>>
 }
 
 impl<'a, T: 'a + Resolvable> Path<'a, T> {
     pub fn new(nodes: Vec<&'a Node<T>>) -> Self {
-        Path { nodes: nodes }
+        Path { nodes, cycles: vec![] }
     }
 
     pub fn append(mut self, node: &'a Node<T>) -> Self {
@@ -17,35 +23,82 @@ impl<'a, T: 'a + Resolvable> Path<'a, T> {
         self
     }
 
+    /// The path, closing `cycle` too.
+    pub fn closing(mut self, cycle: Vec<T::Id>) -> Self {
+        self.add_cycle(cycle);
+        self
+    }
+
     pub fn chain(elements: Vec<Self>) -> Self {
-        Self::new(
-            elements
-                .into_iter()
-                .flat_map(|path| path.nodes)
-                .collect())
+        let mut chained = Self::new(vec![]);
+
+        for path in elements {
+            chained.nodes.extend(path.nodes);
+            for cycle in path.cycles {
+                chained.add_cycle(cycle);
+            }
+        }
+
+        chained
     }
 
+    /// `prefix` followed by what each of `paths`, all starting with it,
+    /// adds to it. Nodes already there are left out if the resolvable
+    /// shares them, see `Resolvable::shares_nodes`.
+    pub fn combine(prefix: &Self, paths: Vec<Self>) -> Self {
+        let mut combined = prefix.clone();
+
+        for path in paths {
+            let suffix = path.suffix(prefix);
+
+            for node in suffix.nodes {
+                if !(T::shares_nodes() && combined.has(node)) {
+                    combined.nodes.push(node);
+                }
+            }
+            for cycle in suffix.cycles {
+                combined.add_cycle(cycle);
+            }
+        }
+
+        combined
+    }
+
+    fn add_cycle(&mut self, cycle: Vec<T::Id>) {
+        if !self.cycles.contains(&cycle) {
+            self.cycles.push(cycle);
+        }
+    }
+
+    /// Nodes past `prefix`. Panics if the path doesn't start with it, see
+    /// `try_suffix`.
     pub fn suffix(&self, prefix: &Self) -> Self {
+        self.try_suffix(prefix).expect("path doesn't start with the prefix")
+    }
+
+    /// Nodes past `prefix`, if the path starts with it, along with the
+    /// cycles `prefix` doesn't close.
+    pub fn try_suffix(&self, prefix: &Self) -> Result<Self, Error> {
         let long = &self.nodes;
         let short = &prefix.nodes;
 
-        assert!({
-            let long_prefix: Vec<&&Node<T>> = long
-                .iter()
-                .take(short.len())
-                .collect();
-            let short_: Vec<&&Node<T>> = short
+        if !long.starts_with(short) {
+            return Err(Error::NotAPrefix);
+        }
+
+        Ok(Path {
+            nodes: long[short.len()..].to_vec(),
+            cycles: self.cycles
                 .iter()
-                .collect();
-            long_prefix == short_
-        });
+                .filter(|cycle| !prefix.cycles.contains(cycle))
+                .cloned()
+                .collect()
+        })
+    }
 
-        Self::new(
-            long
-                .into_iter()
-                .skip(short.len())
-                .map(|&n| n)
-                .collect())
+    /// Checks if the path has `node`, or a node equal to it.
+    pub fn has(&self, node: &Node<T>) -> bool {
+        self.nodes.iter().any(|&known| ptr::eq(known, node) || known == node)
     }
 
     pub fn unique(&self, el: &Node<T>) -> bool {
@@ -56,28 +109,70 @@ impl<'a, T: 'a + Resolvable> Path<'a, T> {
         self.nodes.iter().map(|x| x.id.clone()).collect()
     }
 
+    /// Checks if some nodes can't be selected together, because their ids
+    /// conflict or one of them declares it.
     pub fn conflict(&self) -> bool {
-        T::Id::are_conflicting(&self.idents())
+        T::Id::are_conflicting(&self.idents()) || self.excluding().is_some()
+    }
+
+    /// First node declaring it can't be selected with another one of the
+    /// path.
+    pub fn excluding(&self) -> Option<&'a Node<T>> {
+        self.nodes
+            .iter()
+            .find(|node| node
+                .exclusions()
+                .iter()
+                .any(|id| *id != node.id && self.nodes.iter().any(|other| other.id == *id)))
+            .cloned()
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use context::Context;
     use node::resolved::Resolved;
     use package::ident::SimpleUnique;
+    use package::ident::Semver;
+    use package::ident::Versioned;
     use super::*;
 
     type N = Node<MockResolvable>;
 
     #[derive(Clone, Debug, Eq, Hash, PartialEq)]
-    struct MockResolvable {}
+    struct MockResolvable {
+        exclusions: Vec<SimpleUnique>
+    }
 
     impl Resolvable for MockResolvable {
         type Id = SimpleUnique;
 
-        fn resolve<'a>(&'a self, path: Path<'a, Self>) -> Resolved<'a, Self> {
+        fn resolve<'a>(&'a self, path: Path<'a, Self>, _context: &mut Context<'a, Self>) -> Resolved<'a, Self> {
+            Resolved::success(path)
+        }
+
+        fn alternatives(&self) -> Vec<Vec<&N>> {
+            vec![]
+        }
+
+        fn exclusions(&self) -> Vec<SimpleUnique> {
+            self.exclusions.clone()
+        }
+    }
+
+    #[derive(Clone, Debug, Eq, Hash, PartialEq)]
+    struct MockVersioned {}
+
+    impl Resolvable for MockVersioned {
+        type Id = Versioned<Semver>;
+
+        fn resolve<'a>(&'a self, path: Path<'a, Self>, _context: &mut Context<'a, Self>) -> Resolved<'a, Self> {
             Resolved::success(path)
         }
+
+        fn alternatives(&self) -> Vec<Vec<&Node<Self>>> {
+            vec![]
+        }
     }
 
     pub fn vec_equal<T: Eq>(a: Vec<T>, b: Vec<T>) -> bool {
@@ -134,6 +229,23 @@ mod tests {
             path_abc);
     }
 
+    #[test]
+    fn combines() {
+        let (a, b, c): (N, N, N) = (
+            Node { id: SimpleUnique { id: "a" }, dependency: None },
+            Node { id: SimpleUnique { id: "b" }, dependency: None },
+            Node { id: SimpleUnique { id: "c" }, dependency: None });
+
+        let prefix = Path::new(vec![&a]);
+        let paths = vec![Path::new(vec![&a, &b, &c]), Path::new(vec![&a, &c])];
+
+        // Nodes aren't shared, so the second c stays and conflicts
+        let combined = Path::combine(&prefix, paths);
+        assert_eq!(combined, Path::new(vec![&a, &b, &c, &c]));
+        assert!(combined.conflict());
+        assert!(combined.has(&c) && !prefix.has(&b));
+    }
+
     #[test]
     fn suffix() {
         let id_a = SimpleUnique { id: "a" };
@@ -160,6 +272,9 @@ mod tests {
         assert_eq!(
             path_abc.suffix(&path_a),
             path_bc);
+
+        assert_eq!(path_bc.try_suffix(&path_a), Err(Error::NotAPrefix));
+        assert_eq!(path_abc.try_suffix(&Path::new(vec![])), Ok(path_abc.clone()));
     }
 
     #[test]
@@ -222,4 +337,48 @@ mod tests {
         let path = path.append(&a);
         assert!(path.conflict());
     }
+
+    #[test]
+    fn declared_conflict() {
+        let id_a = SimpleUnique { id: "a" };
+        let id_b = SimpleUnique { id: "b" };
+        let id_c = SimpleUnique { id: "c" };
+
+        let a: N = Node {
+            id: id_a.clone(),
+            dependency: Some(MockResolvable { exclusions: vec![id_a.clone(), id_c.clone()] })
+        };
+
+        let b: N = Node {
+            id: id_b.clone(),
+            dependency: None
+        };
+
+        let c: N = Node {
+            id: id_c.clone(),
+            dependency: None
+        };
+
+        let path = Path::new(vec![&b, &a]);
+        assert!(!path.conflict());
+        assert_eq!(path.excluding(), None);
+
+        let path = Path::new(vec![&c, &b, &a]);
+        assert!(path.conflict());
+        assert_eq!(path.excluding(), Some(&a));
+    }
+
+    #[test]
+    fn semver_compatible_conflict() {
+        let node = |version: &str| -> Node<MockVersioned> {
+            Node { id: Versioned::semver("a", version.parse().unwrap()), dependency: None }
+        };
+        let (a1, a2, a1_3) = (node("1.2.0"), node("2.0.0"), node("1.3.0"));
+
+        let path = Path::new(vec![&a1, &a2]);
+        assert!(!path.conflict());
+
+        let path = path.append(&a1_3);
+        assert!(path.conflict());
+    }
 }
@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::hash::Hash;
+
+use package::ident::Ident;
+use path::Path;
+use node::resolvable::Resolvable;
+
+/// Criterion to choose between solutions.
+pub trait Objective<Id> {
+    /// Costs of a solution, selecting `ids`, compared lexicographically.
+    /// Lower is better.
+    fn costs(&self, ids: &[Id]) -> Vec<i64>;
+}
+
+/// Prefers solutions selecting fewer packages.
+#[derive(Clone, Debug, Default)]
+pub struct FewestPackages;
+
+impl<Id: Ident> Objective<Id> for FewestPackages {
+    fn costs(&self, ids: &[Id]) -> Vec<i64> {
+        let unique: HashSet<_> = ids.iter().collect();
+        vec![unique.len() as i64]
+    }
+}
+
+/// Prefers solutions selecting fewer packages for which a newer version is
+/// available.
+#[derive(Clone, Debug)]
+pub struct NewestVersions<Id: Ident> {
+    outdated: HashSet<Id>
+}
+
+impl<Id: Ident> NewestVersions<Id> {
+    /// Knows about `available` ids, and tells their name and version with
+    /// `version`.
+    pub fn new<I, F, N, V>(available: I, version: F) -> Self
+        where I: IntoIterator<Item = Id>,
+              F: Fn(&Id) -> (N, V),
+              N: Eq + Hash,
+              V: Ord
+    {
+        let available: Vec<_> = available.into_iter().collect();
+        let mut newest: HashMap<N, V> = HashMap::new();
+
+        for id in available.iter() {
+            let (name, version) = version(id);
+            let newer = match newest.get(&name) {
+                Some(known) => version > *known,
+                None => true
+            };
+            if newer {
+                newest.insert(name, version);
+            }
+        }
+
+        let outdated = available
+            .into_iter()
+            .filter(|id| {
+                let (name, version) = version(id);
+                newest[&name] > version
+            })
+            .collect();
+
+        NewestVersions { outdated }
+    }
+}
+
+impl<Id: Ident> Objective<Id> for NewestVersions<Id> {
+    fn costs(&self, ids: &[Id]) -> Vec<i64> {
+        let unique: HashSet<_> = ids.iter().collect();
+        let outdated = unique
+            .into_iter()
+            .filter(|id| self.outdated.contains(id))
+            .count();
+        vec![outdated as i64]
+    }
+}
+
+/// Prefers solutions closer to `baseline`, counting the packages to add
+/// and to remove.
+#[derive(Clone, Debug)]
+pub struct FewestChanges<Id: Ident> {
+    baseline: HashSet<Id>
+}
+
+impl<Id: Ident> FewestChanges<Id> {
+    pub fn new<I: IntoIterator<Item = Id>>(baseline: I) -> Self {
+        FewestChanges { baseline: baseline.into_iter().collect() }
+    }
+}
+
+impl<Id: Ident> Objective<Id> for FewestChanges<Id> {
+    fn costs(&self, ids: &[Id]) -> Vec<i64> {
+        let selected: HashSet<_> = ids.iter().cloned().collect();
+        let changes = selected.symmetric_difference(&self.baseline).count();
+        vec![changes as i64]
+    }
+}
+
+/// Applies each objective in turn, the next ones breaking ties of the
+/// previous ones.
+pub struct Lexicographic<Id> {
+    objectives: Vec<Box<dyn Objective<Id>>>
+}
+
+impl<Id> Lexicographic<Id> {
+    pub fn new(objectives: Vec<Box<dyn Objective<Id>>>) -> Self {
+        Lexicographic { objectives }
+    }
+}
+
+impl<Id> Objective<Id> for Lexicographic<Id> {
+    fn costs(&self, ids: &[Id]) -> Vec<i64> {
+        self.objectives
+            .iter()
+            .flat_map(|objective| objective.costs(ids))
+            .collect()
+    }
+}
+
+/// Keeps the best of the solutions it is offered, the first one on ties.
+pub struct Best<'a, R: 'a + Resolvable, O> {
+    objective: O,
+    best: Option<(Vec<i64>, Path<'a, R>)>
+}
+
+impl<'a, R: 'a + Resolvable, O: Objective<R::Id>> Best<'a, R, O> {
+    pub fn new(objective: O) -> Self {
+        Best { objective, best: None }
+    }
+
+    pub fn offer(&mut self, path: Path<'a, R>) {
+        let costs = self.objective.costs(&path.idents());
+
+        let better = match self.best {
+            Some((ref best, _)) => costs < *best,
+            None => true
+        };
+
+        if better {
+            self.best = Some((costs, path));
+        }
+    }
+
+    /// Costs of the best solution so far.
+    pub fn costs(&self) -> Option<&Vec<i64>> {
+        self.best.as_ref().map(|(costs, _)| costs)
+    }
+
+    pub fn into_path(self) -> Option<Path<'a, R>> {
+        self.best.map(|(_, path)| path)
+    }
+}
+
+/// Best of `solutions`, looking at one at a time.
+pub fn best<'a, R, O, I>(objective: O, solutions: I) -> Option<Path<'a, R>>
+    where R: 'a + Resolvable,
+          O: Objective<R::Id>,
+          I: IntoIterator<Item = Path<'a, R>>
+{
+    let mut best = Best::new(objective);
+
+    for path in solutions {
+        best.offer(path);
+    }
+
+    best.into_path()
+}
+
+#[cfg(test)]
+mod tests {
+    use context::Context;
+    use node::Node;
+    use node::resolved::Resolved;
+    use package::ident::SimpleUnique;
+    use super::*;
+
+    type N = Node<MockResolvable>;
+
+    #[derive(Clone, Debug, Eq, Hash, PartialEq)]
+    struct MockResolvable {}
+
+    impl Resolvable for MockResolvable {
+        type Id = SimpleUnique;
+
+        fn resolve<'a>(&'a self, path: Path<'a, Self>, _context: &mut Context<'a, Self>) -> Resolved<'a, Self> {
+            Resolved::success(path)
+        }
+
+        fn alternatives(&self) -> Vec<Vec<&N>> {
+            vec![]
+        }
+    }
+
+    fn ids(names: &[&'static str]) -> Vec<SimpleUnique> {
+        names.iter().map(|&id| SimpleUnique { id }).collect()
+    }
+
+    /// "name-version" ids
+    fn version(id: &SimpleUnique) -> (&'static str, u32) {
+        let mut parts = id.id.split('-');
+        (parts.next().unwrap(), parts.next().unwrap().parse().unwrap())
+    }
+
+    #[test]
+    fn counts_packages() {
+        assert_eq!(FewestPackages.costs(&ids(&["a", "b", "a"])), vec![2]);
+    }
+
+    #[test]
+    fn counts_outdated() {
+        let newest = NewestVersions::new(ids(&["a-1", "a-2", "b-1", "c-3", "c-1"]), version);
+
+        assert_eq!(newest.costs(&ids(&["a-2", "b-1", "c-3"])), vec![0]);
+        assert_eq!(newest.costs(&ids(&["a-1", "b-1", "c-1"])), vec![2]);
+    }
+
+    #[test]
+    fn counts_changes() {
+        let changes = FewestChanges::new(ids(&["a", "b"]));
+
+        assert_eq!(changes.costs(&ids(&["a", "b"])), vec![0]);
+        assert_eq!(changes.costs(&ids(&["a", "c"])), vec![2]);
+    }
+
+    #[test]
+    fn combines_lexicographically() {
+        let objective: Lexicographic<SimpleUnique> = Lexicographic::new(vec![
+            Box::new(FewestChanges::new(ids(&["a"]))),
+            Box::new(FewestPackages)]);
+
+        assert_eq!(objective.costs(&ids(&["a", "b"])), vec![1, 2]);
+    }
+
+    #[test]
+    fn keeps_first_best() {
+        let nodes: Vec<N> = ["a", "b", "c"]
+            .iter()
+            .map(|&id| Node { id: SimpleUnique { id }, dependency: None })
+            .collect();
+        let (a, b, c) = (&nodes[0], &nodes[1], &nodes[2]);
+
+        let solutions = vec![
+            Path::new(vec![a, b, c]),
+            Path::new(vec![a, c]),
+            Path::new(vec![b, c]),
+            Path::new(vec![a, b, c])];
+
+        assert_eq!(best(FewestPackages, solutions), Some(Path::new(vec![a, c])));
+        assert_eq!(best(FewestPackages, vec![]), None::<Path<MockResolvable>>);
+    }
+}
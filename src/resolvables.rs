@@ -0,0 +1,225 @@
+use context::Context;
+use node::Node;
+use node::resolvable::Resolvable;
+use node::resolved::Resolved;
+use package::ident::Ident;
+use path::Path;
+
+/// Dependency on a single node.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Simple<Id: Ident> {
+    pub node: Box<Node<Simple<Id>>>
+}
+
+impl<Id: Ident> Resolvable for Simple<Id> {
+    type Id = Id;
+
+    fn resolve<'a>(&'a self, path: Path<'a, Self>, context: &mut Context<'a, Self>) -> Resolved<'a, Self> {
+        let wanted = context.wanted();
+        self.node.solve_wanting(path, wanted, context)
+    }
+
+    fn alternatives(&self) -> Vec<Vec<&Node<Self>>> {
+        vec![vec![&self.node]]
+    }
+}
+
+impl<Id: Ident> Simple<Id> {
+    pub fn new(node: &Node<Simple<Id>>) -> Self {
+        Simple {
+            node: Box::new(node.clone())
+        }
+    }
+}
+
+/// Dependency on any one of several nodes, in order of preference.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Any<Id: Ident> {
+    pub deps: Vec<Node<Any<Id>>>
+}
+
+impl<Id: Ident> Resolvable for Any<Id> {
+    type Id = Id;
+
+    fn resolve<'a>(&'a self, path: Path<'a, Self>, context: &mut Context<'a, Self>) -> Resolved<'a, Self> {
+        let wanted = context.wanted();
+        let results = self.deps
+            .iter()
+            .map(|node| node.solve_wanting(path.clone(), wanted, context));
+
+        Resolved::merge_wanted(results, wanted)
+    }
+
+    fn alternatives(&self) -> Vec<Vec<&Node<Self>>> {
+        self.deps
+            .iter()
+            .map(|node| vec![node])
+            .collect()
+    }
+}
+
+impl<Id: Ident> Any<Id> {
+    pub fn new(nodes: Vec<&Node<Any<Id>>>) -> Self {
+        let deps = nodes
+            .into_iter()
+            .cloned()
+            .collect();
+
+        Any {
+            deps
+        }
+    }
+}
+
+/// Boolean equation over nodes: any of several sets of nodes, all of which
+/// are needed together.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct OrAnd<Id: Ident> {
+    pub or_dep: OrDependency<Id>,
+    /// Ids the owning node declares it can't be selected with
+    pub exclusions: Vec<Id>
+}
+
+impl<Id: Ident> Resolvable for OrAnd<Id> {
+    type Id = Id;
+
+    fn resolve<'a>(&'a self, path: Path<'a, Self>, context: &mut Context<'a, Self>) -> Resolved<'a, Self> {
+        self.or_dep.resolve_step(path, context)
+    }
+
+    fn alternatives(&self) -> Vec<Vec<&Node<Self>>> {
+        self.or_dep.and_deps
+            .iter()
+            .map(|and_dep| and_dep.deps.iter().collect())
+            .collect()
+    }
+
+    fn exclusions(&self) -> Vec<Id> {
+        self.exclusions.clone()
+    }
+}
+
+impl<Id: Ident> OrAnd<Id> {
+    pub fn new(or_dep: OrDependency<Id>) -> Self {
+        OrAnd { or_dep, exclusions: vec![] }
+    }
+
+    pub fn excluding(or_dep: OrDependency<Id>, exclusions: Vec<Id>) -> Self {
+        OrAnd { or_dep, exclusions }
+    }
+}
+
+/// Alternatives of an `OrAnd`, in order of preference.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct OrDependency<Id: Ident> {
+    pub and_deps: Vec<AndDependency<Id>>
+}
+
+impl<Id: Ident> OrDependency<Id> {
+    pub fn new(and_deps: Vec<AndDependency<Id>>) -> Self {
+        OrDependency { and_deps }
+    }
+
+    pub fn single(and_dep: AndDependency<Id>) -> Self {
+        Self::new(vec![and_dep])
+    }
+
+    pub fn resolve_step<'a>(&'a self, path: Path<'a, OrAnd<Id>>, context: &mut Context<'a, OrAnd<Id>>) -> Resolved<'a, OrAnd<Id>> {
+        let wanted = context.wanted();
+        let results = self.and_deps
+            .iter()
+            .map(|dep| dep.resolve_step(path.clone(), context));
+
+        Resolved::merge_wanted(results, wanted)
+    }
+}
+
+/// Nodes of an `OrAnd` alternative, all needed together.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct AndDependency<Id: Ident> {
+    pub deps: Vec<Node<OrAnd<Id>>>
+}
+
+impl<Id: Ident> AndDependency<Id> {
+    pub fn new(deps: Vec<Node<OrAnd<Id>>>) -> Self {
+        AndDependency { deps }
+    }
+
+    pub fn single(dep: Node<OrAnd<Id>>) -> Self {
+        Self::new(vec![dep])
+    }
+
+    pub fn resolve_step<'a>(&'a self, path: Path<'a, OrAnd<Id>>, context: &mut Context<'a, OrAnd<Id>>) -> Resolved<'a, OrAnd<Id>> {
+        let wanted = context.wanted();
+        let nodes: Vec<_> = self.deps.iter().collect();
+        Node::solve_together(&nodes, path, wanted, context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use package::ident::SimpleUnique;
+    use super::*;
+
+    fn leaf<R: Resolvable<Id = SimpleUnique>>(id: &'static str) -> Node<R> {
+        Node { id: SimpleUnique::new(id), dependency: None }
+    }
+
+    fn ids<R: Resolvable<Id = SimpleUnique>>(resolved: &Resolved<R>) -> Vec<Vec<&'static str>> {
+        resolved.paths
+            .iter()
+            .map(|path| path.idents().into_iter().map(|id| id.id).collect())
+            .collect()
+    }
+
+    #[test]
+    fn solves_simple_chains() {
+        // a -> b -> c
+        let c = leaf("c");
+        let b = Node { id: SimpleUnique::new("b"), dependency: Some(Simple::new(&c)) };
+        let a = Node { id: SimpleUnique::new("a"), dependency: Some(Simple::new(&b)) };
+
+        assert_eq!(ids(&a.solve(Path::new(vec![]))), vec![vec!["a", "b", "c"]]);
+        assert_eq!(a.dependency.as_ref().unwrap().alternatives(), vec![vec![&b]]);
+    }
+
+    #[test]
+    fn solves_any_alternative() {
+        // a -> b | c | a
+        let (b, c) = (leaf("b"), leaf("c"));
+        let mut a = leaf("a");
+        a.dependency = Some(Any::new(vec![&b, &c, &a.clone()]));
+
+        assert_eq!(ids(&a.solve(Path::new(vec![]))), vec![vec!["a", "b"], vec!["a", "c"]]);
+        assert_eq!(a.dependency.as_ref().unwrap().alternatives().len(), 3);
+    }
+
+    #[test]
+    fn solves_or_and() {
+        // a -> (b & c) | (b & d), c -> f, c excludes b, d -> e
+        let b: Node<OrAnd<SimpleUnique>> = leaf("b");
+        let c = Node {
+            id: SimpleUnique::new("c"),
+            dependency: Some(OrAnd::excluding(
+                OrDependency::single(AndDependency::single(leaf("f"))),
+                vec![SimpleUnique::new("b")]))
+        };
+        let d = Node {
+            id: SimpleUnique::new("d"),
+            dependency: Some(OrAnd::new(OrDependency::single(AndDependency::single(leaf("e")))))
+        };
+        let a = Node {
+            id: SimpleUnique::new("a"),
+            dependency: Some(OrAnd::new(OrDependency::new(vec![
+                AndDependency::new(vec![b.clone(), c.clone()]),
+                AndDependency::new(vec![b.clone(), d.clone()])])))
+        };
+
+        // c can be selected, only not along with b
+        assert_eq!(ids(&c.solve(Path::new(vec![]))), vec![vec!["c", "f"]]);
+        assert_eq!(ids(&a.solve(Path::new(vec![]))), vec![vec!["a", "b", "d", "e"]]);
+        assert_eq!(
+            a.dependency.as_ref().unwrap().alternatives(),
+            vec![vec![&b, &c], vec![&b, &d]]);
+    }
+}
@@ -0,0 +1,103 @@
+use std::ops::Not;
+
+/// Boolean variable, numbered from 0.
+pub type Variable = usize;
+
+/// Variable or its negation.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Literal {
+    code: usize
+}
+
+impl Literal {
+    pub fn positive(variable: Variable) -> Self {
+        Literal { code: variable * 2 }
+    }
+
+    pub fn negative(variable: Variable) -> Self {
+        Literal { code: variable * 2 + 1 }
+    }
+
+    pub fn variable(self) -> Variable {
+        self.code / 2
+    }
+
+    pub fn is_negative(self) -> bool {
+        self.code % 2 == 1
+    }
+
+    /// Dense index, distinct for every literal.
+    pub fn index(self) -> usize {
+        self.code
+    }
+}
+
+impl Not for Literal {
+    type Output = Literal;
+
+    fn not(self) -> Literal {
+        Literal { code: self.code ^ 1 }
+    }
+}
+
+/// Disjunction of literals.
+pub type Clause = Vec<Literal>;
+
+/// Formula in conjunctive normal form.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Cnf {
+    pub variables: usize,
+    pub clauses: Vec<Clause>
+}
+
+impl Cnf {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allocates a fresh variable.
+    pub fn variable(&mut self) -> Variable {
+        self.variables += 1;
+        self.variables - 1
+    }
+
+    pub fn add(&mut self, clause: Clause) {
+        self.clauses.push(clause);
+    }
+
+    /// Checks if `model` satisfies every clause.
+    pub fn satisfied_by(&self, model: &[bool]) -> bool {
+        self.clauses
+            .iter()
+            .all(|clause| clause
+                .iter()
+                .any(|lit| model[lit.variable()] != lit.is_negative()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negates() {
+        let lit = Literal::positive(3);
+
+        assert_eq!(lit.variable(), 3);
+        assert!(!lit.is_negative());
+        assert_eq!(!lit, Literal::negative(3));
+        assert_eq!(!!lit, lit);
+    }
+
+    #[test]
+    fn checks_models() {
+        let mut cnf = Cnf::new();
+        let (a, b) = (cnf.variable(), cnf.variable());
+
+        cnf.add(vec![Literal::positive(a), Literal::positive(b)]);
+        cnf.add(vec![Literal::negative(a)]);
+
+        assert!(cnf.satisfied_by(&[false, true]));
+        assert!(!cnf.satisfied_by(&[true, true]));
+    }
+}
@@ -0,0 +1,1277 @@
+pub mod cnf;
+pub mod solver;
+
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::slice;
+
+use error::Error;
+use error::Result;
+use explanation::Explanation;
+use explanation::Incompatibility;
+use package::dependency::Dependency;
+use package::dependency::Scope;
+use package::feature::Feature;
+use package::ident::Ident;
+use package::marker::Environment;
+use package::package::Package;
+use package::repository::Repository;
+use self::cnf::Cnf;
+use self::cnf::Literal;
+use self::cnf::Variable;
+use self::solver::Solver;
+use self::solver::Step;
+
+/// Selects packages for `root` with the SAT solver.
+///
+/// Returns the selected ids, root first, in the order a depth-first
+/// traversal of the chosen dependencies visits them.
+pub fn solve<P: Package>(repository: &Repository<P>, root: &P::Id) -> Option<Vec<P::Id>> {
+    select(repository, root).map(|selection| selection.ids)
+}
+
+/// Packages selected for a root, and what became of the weak dependencies
+/// of the selected packages, each with the package declaring it and the
+/// platform that package is selected on.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Selection<Id: Ident> {
+    pub ids: Vec<Id>,
+    /// Packages selected for the host, to build the others
+    pub host: Vec<Id>,
+    pub satisfied: Vec<(Id, Platform, Dependency<Id>)>,
+    pub skipped: Vec<(Id, Platform, Dependency<Id>)>,
+    /// Features turned on for each selected package, unified over all the
+    /// dependencies asking for them
+    pub features: Features<Id>,
+    pub host_features: Features<Id>
+}
+
+/// Names of the features turned on for each package.
+pub type Features<Id> = HashMap<Id, BTreeSet<String>>;
+
+/// Selects packages for `root` like `solve` does, and tells which weak
+/// dependencies were satisfied.
+pub fn select<P: Package>(repository: &Repository<P>, root: &P::Id) -> Option<Selection<P::Id>> {
+    select_with_features(repository, root, &[])
+}
+
+/// Selects packages for `root` with `features` turned on.
+pub fn select_with_features<P: Package>(repository: &Repository<P>, root: &P::Id, features: &[&str]) -> Option<Selection<P::Id>> {
+    select_for(repository, root, &Request::new().features(features))
+}
+
+/// How to resolve a root: the features to turn on for it, the
+/// environments to resolve in, and the scopes of the dependencies to
+/// resolve.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Request {
+    pub features: Vec<String>,
+    /// Environments the markers of the dependencies are evaluated in. A
+    /// dependency applies if its markers hold in any of them, and all
+    /// dependencies apply without any.
+    pub environments: Vec<Environment>,
+    /// `Normal` and `Build` unless told otherwise
+    pub scopes: Vec<Scope>
+}
+
+impl Default for Request {
+    fn default() -> Self {
+        Request {
+            features: vec![],
+            environments: vec![],
+            scopes: vec![Scope::Normal, Scope::Build]
+        }
+    }
+}
+
+impl Request {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also resolves the dependencies of `scope`.
+    pub fn with_scope(mut self, scope: Scope) -> Self {
+        if !self.scopes.contains(&scope) {
+            self.scopes.push(scope);
+        }
+        self
+    }
+
+    /// Leaves out the dependencies of `scope`.
+    pub fn without_scope(mut self, scope: Scope) -> Self {
+        self.scopes.retain(|&other| other != scope);
+        self
+    }
+
+    pub fn features(mut self, features: &[&str]) -> Self {
+        self.features = features.iter().map(|name| name.to_string()).collect();
+        self
+    }
+
+    /// Adds an environment to resolve in.
+    pub fn environment(mut self, environment: Environment) -> Self {
+        self.environments.push(environment);
+        self
+    }
+}
+
+/// Resolution shared by some environments.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Fork<Id: Ident> {
+    pub environments: Vec<Environment>,
+    /// Packages for all the environments together
+    pub selection: Selection<Id>,
+    /// Packages each environment installs, in the order of `environments`
+    pub installs: Vec<Vec<Id>>
+}
+
+/// Selects packages for `root` as `request` asks.
+///
+/// With several environments, the selection is valid in all of them at
+/// once: a package has the same version everywhere it is needed.
+pub fn select_for<P: Package>(repository: &Repository<P>, root: &P::Id, request: &Request) -> Option<Selection<P::Id>> {
+    let encoding = Encoding::for_request(repository, root, request)?;
+    let model = settle(&encoding)?;
+
+    Some(encoding.selection(&model))
+}
+
+/// Resolves `root` for all the environments of `request`, with as few
+/// forks as it finds.
+///
+/// Environments are taken in order, each joining the first fork it can be
+/// resolved along with, or starting a new one. `None` if an environment
+/// can't be resolved at all. Without environments, there is a single fork
+/// with all dependencies applying.
+pub fn universal<P: Package>(repository: &Repository<P>, root: &P::Id, request: &Request) -> Option<Vec<Fork<P::Id>>> {
+    let fork = |environments: Vec<Environment>| -> Option<Fork<P::Id>> {
+        let request = Request { environments, ..request.clone() };
+        let encoding = Encoding::for_request(repository, root, &request)?;
+        let model = settle(&encoding)?;
+
+        let installs = request.environments
+            .iter()
+            .map(|environment| encoding.walk(&model, slice::from_ref(environment)).ids(Platform::Target))
+            .collect();
+
+        Some(Fork {
+            selection: encoding.selection(&model),
+            environments: request.environments,
+            installs
+        })
+    };
+
+    if request.environments.is_empty() {
+        return fork(vec![]).map(|fork| vec![fork]);
+    }
+
+    let mut forks: Vec<Fork<P::Id>> = vec![];
+    for environment in request.environments.iter() {
+        let joined = forks.iter().enumerate().find_map(|(i, existing)| {
+            let mut environments = existing.environments.clone();
+            environments.push(environment.clone());
+            fork(environments).map(|joined| (i, joined))
+        });
+
+        match joined {
+            Some((i, joined)) => forks[i] = joined,
+            None => forks.push(fork(vec![environment.clone()])?)
+        }
+    }
+
+    Some(forks)
+}
+
+/// Model of `encoding` satisfying its hard dependencies, and as many weak
+/// ones as it can.
+///
+/// Once the hard dependencies are satisfied, weak dependencies of the
+/// selected packages are made hard one at a time if they can be,
+/// recommendations of all packages before any suggestion.
+fn settle<P: Package>(encoding: &Encoding<P>) -> Option<Vec<bool>> {
+    let mut solver = Solver::new(&encoding.cnf);
+    let mut model = solver.solve()?;
+    let mut tried = HashSet::new();
+
+    loop {
+        let selected: HashSet<_> = encoding.walk(&model, &encoding.environments).units.into_iter().collect();
+        let mut pending: Vec<_> = (0..encoding.weak.len())
+            .filter(|i| {
+                let ((pkg, platform), condition, _) = encoding.weak[*i];
+                !tried.contains(i) && selected.contains(&(pkg.id(), platform)) && model[condition.variable()]
+            })
+            .collect();
+
+        let suggestion = |i: &usize| matches!(encoding.weak[*i].2.unconditional(), Dependency::Suggests(_));
+        if !pending.iter().all(suggestion) {
+            pending.retain(|i| !suggestion(i));
+        }
+        if pending.is_empty() {
+            break;
+        }
+
+        for i in pending {
+            tried.insert(i);
+            if let Some(found) = encoding.strengthen(&mut solver, i) {
+                model = found;
+            }
+        }
+    }
+
+    Some(model)
+}
+
+/// Explains why `root` can't be resolved, `None` if it can.
+pub fn explain<P: Package>(repository: &Repository<P>, root: &P::Id) -> Result<Option<Explanation<P::Id>>, P::Id> {
+    explain_with_features(repository, root, &[])
+}
+
+/// Explains why `root` can't be resolved with `features` turned on, `None`
+/// if it can.
+pub fn explain_with_features<P: Package>(repository: &Repository<P>, root: &P::Id, features: &[&str]) -> Result<Option<Explanation<P::Id>>, P::Id> {
+    explain_for(repository, root, &Request::new().features(features))
+}
+
+/// Explains why `root` can't be resolved as `request` asks, `None` if it
+/// can. Fails if the solver found no solution without a refutation to show
+/// for it.
+pub fn explain_for<P: Package>(repository: &Repository<P>, root: &P::Id, request: &Request) -> Result<Option<Explanation<P::Id>>, P::Id> {
+    let encoding = match Encoding::for_request(repository, root, request) {
+        Some(encoding) => encoding,
+        None => return Ok(Some(Explanation::new(vec![
+            Incompatibility::Missing(root.clone()),
+            Incompatibility::Derived { selected: vec![], required: vec![], because: vec![0] }])))
+    };
+
+    let mut solver = Solver::new(&encoding.cnf);
+    match solver.solve() {
+        Some(_) => Ok(None),
+        None => match solver.refutation() {
+            Some(refutation) => Ok(Some(encoding.explain(&refutation))),
+            None => Err(Error::unexplained(root))
+        }
+    }
+}
+
+/// What a clause of an `Encoding` stands for.
+#[derive(Clone, Debug)]
+enum Origin<Id> {
+    Root(Id),
+    /// Package and all the ids of its dependency, including missing ones
+    Dependency(Id, Vec<Id>),
+    /// Package and the range or virtual package it needs, matching nothing
+    Unmatched(Id, String),
+    /// Packages kept apart on a platform
+    Conflict(Id, Id, Platform),
+    Breaks(Id, Id, Platform),
+    /// Root and one of the features requested for it
+    Feature(Id, String),
+    /// Package turning on a feature of another one
+    Enables(Id, Id, String),
+    MissingFeature(Id, String),
+    /// Feature belonging to its package or turning on another one
+    Definition
+}
+
+/// Where a package is built for: the target, or the host running the
+/// builds of the target packages.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Platform {
+    Target,
+    Host
+}
+
+/// Package built for a platform.
+type Unit<'r, P> = (&'r P, Platform);
+
+/// Features turned on for each package of each platform.
+type UnitFeatures<Id> = HashMap<(Id, Platform), BTreeSet<String>>;
+
+/// Packages a model selects, in the order they are walked, with their
+/// features.
+struct Walked<Id> {
+    units: Vec<(Id, Platform)>,
+    features: UnitFeatures<Id>
+}
+
+impl<Id: Ident> Walked<Id> {
+    fn ids(&self, platform: Platform) -> Vec<Id> {
+        self.units
+            .iter()
+            .filter(|unit| unit.1 == platform)
+            .map(|unit| unit.0.clone())
+            .collect()
+    }
+
+    fn features(&self, platform: Platform) -> Features<Id> {
+        self.features
+            .iter()
+            .filter(|(unit, _)| unit.1 == platform)
+            .map(|(unit, names)| (unit.0.clone(), names.clone()))
+            .collect()
+    }
+}
+
+/// Packages reachable from a root, as a SAT problem.
+///
+/// Every package is a variable for each platform it is reachable on, true
+/// if the package is selected there, and so is every feature of a package,
+/// true if it is turned on.
+/// The root has to be selected for the target, every hard dependency of a
+/// selected package or feature needs one of its matches selected, and
+/// conflicting packages exclude each other on the same platform, whether
+/// their ids conflict or one declares it. Build dependencies are selected
+/// for the host, so they can differ from those of the target.
+/// Dependencies whose markers hold in none of the environments, or whose
+/// scope isn't requested, are left out.
+/// Negative dependencies whose markers only hold in some environments only
+/// keep packages apart where they are both installed: with those, every
+/// package also gets a variable for each environment, true if the package
+/// is walked there, see `universal`.
+/// In explanations, features stand for their package.
+#[derive(Debug)]
+pub struct Encoding<'r, P: 'r + Package> {
+    pub cnf: Cnf,
+    root: &'r P,
+    repository: &'r Repository<P>,
+    variables: HashMap<(P::Id, Platform), Variable>,
+    features: HashMap<(P::Id, Platform, String), Variable>,
+    /// Whether each package is installed in each environment, by position,
+    /// if some negative dependencies need it
+    installed: HashMap<(P::Id, Platform, usize), Variable>,
+    /// Features requested for the root
+    requested: Vec<String>,
+    environments: Vec<Environment>,
+    scopes: Vec<Scope>,
+    ids: Vec<P::Id>,
+    origins: Vec<Origin<P::Id>>,
+    /// Weak dependencies, left out of the clauses, with the package
+    /// declaring them and the literal they depend on
+    weak: Vec<(Unit<'r, P>, Literal, Dependency<P::Id>)>
+}
+
+impl<'r, P: 'r + Package> Encoding<'r, P> {
+    /// Encodes the packages reachable from `root`, `None` if the root is not
+    /// in the repository.
+    pub fn new(repository: &'r Repository<P>, root: &P::Id) -> Option<Self> {
+        Self::with_features(repository, root, &[])
+    }
+
+    /// Like `new`, with `features` of the root turned on.
+    pub fn with_features(repository: &'r Repository<P>, root: &P::Id, features: &[&str]) -> Option<Self> {
+        Self::for_request(repository, root, &Request::new().features(features))
+    }
+
+    /// Like `new`, resolving as `request` asks.
+    pub fn for_request(repository: &'r Repository<P>, root: &P::Id, request: &Request) -> Option<Self> {
+        let root = repository.get(root)?;
+
+        let mut encoding = Encoding {
+            cnf: Cnf::new(),
+            root,
+            repository,
+            variables: HashMap::new(),
+            features: HashMap::new(),
+            installed: HashMap::new(),
+            requested: request.features.clone(),
+            environments: request.environments.clone(),
+            scopes: request.scopes.clone(),
+            ids: vec![],
+            origins: vec![],
+            weak: vec![]
+        };
+
+        let units = encoding.reachable();
+        for &(pkg, platform) in units.iter() {
+            let variable = encoding.cnf.variable();
+            encoding.variables.insert((pkg.id(), platform), variable);
+            encoding.ids.push(pkg.id());
+        }
+        for &(pkg, platform) in units.iter() {
+            for (name, _) in sorted_features(pkg) {
+                let variable = encoding.cnf.variable();
+                encoding.features.insert((pkg.id(), platform, name), variable);
+                encoding.ids.push(pkg.id());
+            }
+        }
+
+        let clause = vec![encoding.literal(root, Platform::Target)];
+        encoding.add(clause, Origin::Root(root.id()));
+
+        if units.iter().any(|&unit| encoding.has_scoped_exclusions(unit)) {
+            encoding.track_installs(&units);
+        }
+
+        for name in encoding.requested.clone() {
+            match encoding.feature(root, Platform::Target, &name) {
+                Some(feature) => encoding.add(vec![feature], Origin::Feature(root.id(), name)),
+                None => {
+                    let clause = vec![!encoding.literal(root, Platform::Target)];
+                    encoding.add(clause, Origin::MissingFeature(root.id(), name));
+                }
+            }
+        }
+
+        for &unit in units.iter() {
+            let (pkg, platform) = unit;
+            let selected = encoding.literal(pkg, platform);
+
+            for dep in pkg.dependencies() {
+                encoding.constrain(unit, selected, dep);
+            }
+
+            for (name, feature) in sorted_features(pkg) {
+                let enabled = encoding.feature(pkg, platform, &name).unwrap();
+                encoding.add(vec![!enabled, selected], Origin::Definition);
+
+                for other in feature.enables {
+                    match encoding.feature(pkg, platform, &other) {
+                        Some(other) => encoding.add(vec![!enabled, other], Origin::Definition),
+                        None => encoding.add(vec![!enabled], Origin::MissingFeature(pkg.id(), other))
+                    }
+                }
+
+                for dep in feature.dependencies {
+                    encoding.constrain(unit, enabled, dep);
+                }
+            }
+        }
+
+        for (i, &(a, platform)) in units.iter().enumerate() {
+            for &(b, other) in units[i + 1..].iter() {
+                if platform == other && P::Id::are_conflicting(&[a.id(), b.id()]) {
+                    let clause = vec![!encoding.literal(a, platform), !encoding.literal(b, platform)];
+                    encoding.add(clause, Origin::Conflict(a.id(), b.id(), platform));
+                }
+            }
+        }
+
+        Some(encoding)
+    }
+
+    /// Variable of the package `id` on the target.
+    pub fn variable(&self, id: &P::Id) -> Option<Variable> {
+        self.variables.get(&(id.clone(), Platform::Target)).cloned()
+    }
+
+    /// Selected packages of a model, on the target.
+    ///
+    /// Walks from the root through the first selected match of every
+    /// dependency, dropping packages the model selects needlessly.
+    pub fn decode(&self, model: &[bool]) -> Vec<P::Id> {
+        self.walk(model, &self.environments).ids(Platform::Target)
+    }
+
+    /// Selected packages of a model on each platform, like `decode`, with
+    /// the features turned on for each. Dependencies of the features are
+    /// walked too, and features the model turns on needlessly are dropped
+    /// as well. Only the dependencies applying in `environments` are
+    /// followed.
+    fn walk(&self, model: &[bool], environments: &[Environment]) -> Walked<P::Id> {
+        let mut visited = HashSet::new();
+        let mut walked = Walked { units: vec![], features: HashMap::new() };
+        // Dependencies left to follow, with the package declaring them,
+        // next on top
+        let mut pending = vec![];
+
+        let root = (self.root, Platform::Target);
+        for name in self.requested.iter() {
+            enable(root, name, &mut walked.features, &mut pending);
+        }
+        visited.insert((self.root.id(), Platform::Target));
+        walked.units.push((self.root.id(), Platform::Target));
+        pending.extend(self.root.dependencies().into_iter().rev().map(|dep| (root, dep)));
+
+        while let Some((unit, dep)) = pending.pop() {
+            if !self.keeps(unit, &dep, environments) {
+                continue;
+            }
+
+            let platform = platform(unit.1, &dep);
+            let chosen = self.repository
+                .dependency_matches(&dep)
+                .into_iter()
+                .find(|m| model[self.variables[&(m.id(), platform)]]);
+
+            if let Some(pkg) = chosen {
+                let chosen = (pkg, platform);
+                for name in dep.features() {
+                    enable(chosen, &name, &mut walked.features, &mut pending);
+                }
+
+                if visited.insert((pkg.id(), platform)) {
+                    walked.units.push((pkg.id(), platform));
+                    pending.extend(pkg.dependencies().into_iter().rev().map(|dep| (chosen, dep)));
+                }
+            }
+        }
+
+        for unit in walked.units.iter() {
+            walked.features.entry(unit.clone()).or_default();
+        }
+
+        walked
+    }
+
+    /// Translates a refutation of the encoding into packages terms.
+    pub fn explain(&self, refutation: &[Step]) -> Explanation<P::Id> {
+        let mut incompatibilities = vec![];
+        // Incompatibilities standing for each step
+        let mut lines: Vec<Vec<usize>> = vec![];
+
+        for step in refutation {
+            let added = match step {
+                Step::Input(clause) => self.facts(&self.origins[*clause], &mut incompatibilities),
+                Step::Derived(clause, steps) => {
+                    let mut because: Vec<usize> = steps
+                        .iter()
+                        .flat_map(|&step| lines[step].iter().cloned())
+                        .collect();
+                    because.sort();
+                    because.dedup();
+
+                    let ids = |negative: bool| {
+                        let mut ids: Vec<_> = clause
+                            .iter()
+                            .filter(|lit| lit.is_negative() == negative)
+                            .map(|lit| self.ids[lit.variable()].clone())
+                            .collect();
+                        // Features and their package are the same to the reader
+                        ids.dedup();
+                        ids
+                    };
+
+                    incompatibilities.push(Incompatibility::Derived {
+                        selected: ids(true),
+                        required: ids(false),
+                        because
+                    });
+                    vec![incompatibilities.len() - 1]
+                }
+            };
+            lines.push(added);
+        }
+
+        Explanation::new(incompatibilities)
+    }
+
+    /// Adds the facts behind a clause, reusing already stated ones.
+    fn facts(&self, origin: &Origin<P::Id>, incompatibilities: &mut Vec<Incompatibility<P::Id>>) -> Vec<usize> {
+        let mut facts = match origin {
+            Origin::Root(id) => vec![Incompatibility::Root(id.clone())],
+            Origin::Conflict(a, b, platform) => vec![on(*platform, Incompatibility::Conflict(a.clone(), b.clone()))],
+            Origin::Breaks(a, b, platform) => vec![on(*platform, Incompatibility::Breaks(a.clone(), b.clone()))],
+            Origin::Dependency(id, deps) => {
+                let mut facts = vec![Incompatibility::Dependency(id.clone(), deps.clone())];
+                facts.extend(
+                    deps
+                        .iter()
+                        .filter(|dep| self.repository.get(dep).is_none())
+                        .map(|dep| Incompatibility::Missing(dep.clone())));
+                facts
+            },
+            Origin::Unmatched(id, target) => vec![Incompatibility::Unmatched(id.clone(), target.clone())],
+            Origin::Feature(id, name) => vec![Incompatibility::Feature(id.clone(), name.clone())],
+            Origin::Enables(a, b, name) => vec![Incompatibility::Enables(a.clone(), b.clone(), name.clone())],
+            Origin::MissingFeature(id, name) => vec![Incompatibility::MissingFeature(id.clone(), name.clone())],
+            Origin::Definition => vec![]
+        };
+
+        facts
+            .drain(..)
+            .map(|fact| match incompatibilities.iter().position(|known| *known == fact) {
+                Some(position) => position,
+                None => {
+                    incompatibilities.push(fact);
+                    incompatibilities.len() - 1
+                }
+            })
+            .collect()
+    }
+
+    /// Makes the weak dependency at `index` hard, if one of its matches can
+    /// be selected along with what it depends on. Returns the model showing
+    /// it.
+    fn strengthen(&self, solver: &mut Solver, index: usize) -> Option<Vec<bool>> {
+        let ((_, declaring), condition, ref dep) = self.weak[index];
+        let platform = platform(declaring, dep);
+        let matches = self.repository.dependency_matches(dep);
+
+        let model = matches
+            .iter()
+            .filter_map(|m| solver.solve_with(&[condition, self.literal(m, platform)]))
+            .next()?;
+
+        let mut clause = vec![!condition];
+        clause.extend(matches.iter().map(|m| self.literal(m, platform)));
+        solver.add_clause(clause);
+
+        Some(model)
+    }
+
+    /// Decoded model, with the weak dependencies of the selected packages
+    /// and features sorted out.
+    fn selection(&self, model: &[bool]) -> Selection<P::Id> {
+        let walked = self.walk(model, &self.environments);
+        let selected: HashSet<_> = walked.units.iter().collect();
+        let mut satisfied = vec![];
+        let mut skipped = vec![];
+
+        for unit in walked.units.iter() {
+            let (ref id, declaring) = *unit;
+            let pkg = match self.repository.get(id) {
+                Some(pkg) => pkg,
+                None => continue
+            };
+
+            let mut dependencies = pkg.dependencies();
+            let mut enabled = pkg.features();
+            for name in walked.features[unit].iter() {
+                dependencies.extend(enabled.remove(name).into_iter().flat_map(|feature| feature.dependencies));
+            }
+
+            let weak = dependencies
+                .into_iter()
+                .filter(|dep| dep.weakened().is_some() && self.keeps((pkg, declaring), dep, &self.environments));
+
+            for dep in weak {
+                let platform = platform(declaring, &dep);
+                let matched = self.repository
+                    .dependency_matches(&dep)
+                    .iter()
+                    .any(|m| selected.contains(&(m.id(), platform)));
+
+                match matched {
+                    true => satisfied.push((id.clone(), declaring, dep)),
+                    false => skipped.push((id.clone(), declaring, dep))
+                }
+            }
+        }
+
+        Selection {
+            ids: walked.ids(Platform::Target),
+            host: walked.ids(Platform::Host),
+            satisfied,
+            skipped,
+            features: walked.features(Platform::Target),
+            host_features: walked.features(Platform::Host)
+        }
+    }
+
+    /// Adds the clauses for the dependency `dep` of `unit`, which only
+    /// applies if `condition` holds, and if it is kept.
+    fn constrain(&mut self, unit: Unit<'r, P>, condition: Literal, dep: Dependency<P::Id>) {
+        if !self.keeps(unit, &dep, &self.environments) {
+            return;
+        }
+
+        let (pkg, declaring) = unit;
+        let platform = platform(declaring, &dep);
+
+        if dep.negated().is_some() {
+            self.exclude(unit, platform, condition, &dep);
+            return;
+        }
+
+        self.request(pkg, platform, condition, &dep);
+
+        if dep.weakened().is_some() {
+            self.weak.push((unit, condition, dep));
+            return;
+        }
+
+        let matches = self.repository.dependency_matches(&dep);
+
+        // Missing ids are only known for explicit choices
+        let ids = match dep.unconditional() {
+            Dependency::Choice(ids) => ids.clone(),
+            _ => matches.iter().map(|m| m.id()).collect()
+        };
+
+        let origin = match dep.target() {
+            Some(target) if ids.is_empty() => Origin::Unmatched(pkg.id(), target),
+            _ => Origin::Dependency(pkg.id(), ids)
+        };
+
+        let mut clause = vec![!condition];
+        clause.extend(matches.into_iter().map(|m| self.literal(m, platform)));
+        self.add(clause, origin);
+    }
+
+    /// Turns on the features `dep` asks for in the matches it selects on
+    /// `platform`.
+    fn request(&mut self, pkg: &P, platform: Platform, condition: Literal, dep: &Dependency<P::Id>) {
+        let names = dep.features();
+        if names.is_empty() {
+            return;
+        }
+
+        for m in self.repository.dependency_matches(dep) {
+            for name in names.iter() {
+                let selected = vec![!condition, !self.literal(m, platform)];
+
+                match self.feature(m, platform, name) {
+                    Some(feature) => {
+                        let mut clause = selected;
+                        clause.push(feature);
+                        self.add(clause, Origin::Enables(pkg.id(), m.id(), name.clone()));
+                    },
+                    None => self.add(selected, Origin::MissingFeature(m.id(), name.clone()))
+                }
+            }
+        }
+    }
+
+    /// Keeps `unit` from being selected along with the reachable packages
+    /// on `platform` its negative dependency `dep` rules out, if `condition`
+    /// holds. If `dep` only applies in some environments, only keeps them
+    /// from being installed together in those.
+    fn exclude(&mut self, unit: Unit<'r, P>, platform: Platform, condition: Literal, dep: &Dependency<P::Id>) {
+        let (pkg, declaring) = unit;
+        let excluded: Vec<_> = self.repository
+            .excluded(dep)
+            .into_iter()
+            .filter(|other| (other.id(), platform) != (pkg.id(), declaring))
+            .filter(|other| self.variables.contains_key(&(other.id(), platform)))
+            .collect();
+
+        let environments = match self.is_scoped(dep) {
+            true => (0..self.environments.len())
+                .filter(|&e| dep.applies(&self.environments[e]))
+                .map(Some)
+                .collect(),
+            false => vec![None]
+        };
+
+        for other in excluded {
+            let origin = match dep.unconditional() {
+                Dependency::Breaks(_) => Origin::Breaks(pkg.id(), other.id(), platform),
+                _ => Origin::Conflict(pkg.id(), other.id(), platform)
+            };
+
+            for environment in environments.iter() {
+                let clause = match *environment {
+                    Some(e) => vec![!condition, !self.installed(pkg, declaring, e), !self.installed(other, platform, e)],
+                    None => vec![!condition, !self.literal(other, platform)]
+                };
+                self.add(clause, origin.clone());
+            }
+        }
+    }
+
+    /// Checks if `dep` is negative and only applies in some of the
+    /// environments, so that its exclusions differ between them.
+    fn is_scoped(&self, dep: &Dependency<P::Id>) -> bool {
+        dep.negated().is_some()
+            && self.environments.len() > 1
+            && !self.environments.iter().all(|environment| dep.applies(environment))
+    }
+
+    /// Checks if `unit` or one of its features has a kept negative
+    /// dependency applying in some environments only.
+    fn has_scoped_exclusions(&self, unit: Unit<'r, P>) -> bool {
+        let (pkg, _) = unit;
+        let mut dependencies = pkg.dependencies();
+        dependencies.extend(sorted_features(pkg).into_iter().flat_map(|(_, feature)| feature.dependencies));
+
+        dependencies
+            .iter()
+            .any(|dep| self.is_scoped(dep) && self.keeps(unit, dep, &self.environments))
+    }
+
+    /// Adds a variable for each of `units` in each environment, true if the
+    /// unit is installed there.
+    ///
+    /// A unit is installed if it is selected and `walk` reaches it in the
+    /// environment: the root is, and so is the first selected match of
+    /// every dependency applying there, of an installed unit or of one of
+    /// its features turned on.
+    fn track_installs(&mut self, units: &[Unit<'r, P>]) {
+        for e in 0..self.environments.len() {
+            for &(pkg, platform) in units.iter() {
+                let variable = self.cnf.variable();
+                self.installed.insert((pkg.id(), platform, e), variable);
+                self.ids.push(pkg.id());
+            }
+        }
+
+        for e in 0..self.environments.len() {
+            let clause = vec![self.installed(self.root, Platform::Target, e)];
+            self.add(clause, Origin::Root(self.root.id()));
+
+            for &unit in units.iter() {
+                let (pkg, declaring) = unit;
+                let installed = self.installed(pkg, declaring, e);
+                self.add(vec![!installed, self.literal(pkg, declaring)], Origin::Definition);
+
+                let mut dependencies: Vec<_> = pkg.dependencies().into_iter().map(|dep| (None, dep)).collect();
+                for (name, feature) in sorted_features(pkg) {
+                    let enabled = self.feature(pkg, declaring, &name);
+                    dependencies.extend(feature.dependencies.into_iter().map(|dep| (enabled, dep)));
+                }
+
+                let environment = [self.environments[e].clone()];
+                for (enabled, dep) in dependencies {
+                    if dep.negated().is_some() || !self.keeps(unit, &dep, &environment) {
+                        continue;
+                    }
+
+                    let platform = platform(declaring, &dep);
+                    let matches = self.repository.dependency_matches(&dep);
+
+                    for (i, m) in matches.iter().enumerate() {
+                        // Installed with the unit, unless an earlier match is
+                        // selected
+                        let mut clause = vec![!installed, !self.literal(m, platform)];
+                        clause.extend(enabled.map(|enabled| !enabled));
+                        clause.extend(matches[..i].iter().map(|earlier| self.literal(earlier, platform)));
+                        clause.push(self.installed(m, platform, e));
+                        self.add(clause, Origin::Definition);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Checks if the dependency `dep` of `unit` is to be resolved: its
+    /// markers hold in any of `environments`, and its scope is requested.
+    /// Development and test dependencies only count for the root.
+    fn keeps(&self, unit: Unit<'r, P>, dep: &Dependency<P::Id>, environments: &[Environment]) -> bool {
+        let scope = dep.scope();
+        let root = unit.0.id() == self.root.id() && unit.1 == Platform::Target;
+
+        applies(dep, environments)
+            && self.scopes.contains(&scope)
+            && (root || !matches!(scope, Scope::Dev | Scope::Test))
+    }
+
+    fn add(&mut self, clause: Vec<Literal>, origin: Origin<P::Id>) {
+        self.cnf.add(clause);
+        self.origins.push(origin);
+    }
+
+    fn literal(&self, pkg: &P, platform: Platform) -> Literal {
+        Literal::positive(self.variables[&(pkg.id(), platform)])
+    }
+
+    fn installed(&self, pkg: &P, platform: Platform, environment: usize) -> Literal {
+        Literal::positive(self.installed[&(pkg.id(), platform, environment)])
+    }
+
+    fn feature(&self, pkg: &P, platform: Platform, name: &str) -> Option<Literal> {
+        self.features
+            .get(&(pkg.id(), platform, name.to_string()))
+            .map(|&variable| Literal::positive(variable))
+    }
+
+    /// Packages reachable from the root, through the dependencies of the
+    /// packages or any of their features, on the platforms they are
+    /// reachable on.
+    fn reachable(&self) -> Vec<Unit<'r, P>> {
+        let mut visited = HashSet::new();
+        let mut units = vec![];
+        let mut pending = vec![(self.root, Platform::Target)];
+
+        while let Some(unit) = pending.pop() {
+            let (pkg, declaring) = unit;
+            if visited.insert((pkg.id(), declaring)) {
+                units.push(unit);
+
+                let mut dependencies = pkg.dependencies();
+                dependencies.extend(sorted_features(pkg).into_iter().flat_map(|(_, feature)| feature.dependencies));
+
+                for dep in dependencies.iter().filter(|dep| self.keeps(unit, dep, &self.environments)) {
+                    let platform = platform(declaring, dep);
+                    pending.extend(self.repository.dependency_matches(dep).into_iter().map(|m| (m, platform)));
+                }
+            }
+        }
+
+        units
+    }
+}
+
+/// Checks if the markers of `dep` hold in any of `environments`, or if
+/// there are none.
+fn applies<Id: Ident>(dep: &Dependency<Id>, environments: &[Environment]) -> bool {
+    environments.is_empty() || environments.iter().any(|environment| dep.applies(environment))
+}
+
+/// `incompatibility`, said to hold on the host if `platform` is.
+fn on<Id>(platform: Platform, incompatibility: Incompatibility<Id>) -> Incompatibility<Id> {
+    match platform {
+        Platform::Target => incompatibility,
+        Platform::Host => Incompatibility::OnHost(Box::new(incompatibility))
+    }
+}
+
+/// Platform the matches of `dep` are selected on, when declared on
+/// `declaring`.
+fn platform<Id: Ident>(declaring: Platform, dep: &Dependency<Id>) -> Platform {
+    match dep.scope() {
+        Scope::Build => Platform::Host,
+        _ => declaring
+    }
+}
+
+/// Features of `pkg`, by name.
+fn sorted_features<P: Package>(pkg: &P) -> Vec<(String, Feature<P::Id>)> {
+    let mut features: Vec<_> = pkg.features().into_iter().collect();
+    features.sort_by(|a, b| a.0.cmp(&b.0));
+    features
+}
+
+/// Turns on the feature `name` of `unit` and those it turns on, queueing
+/// their dependencies. Unknown features are left out.
+fn enable<'r, P: Package>(unit: Unit<'r, P>, name: &str, enabled: &mut UnitFeatures<P::Id>, pending: &mut Vec<(Unit<'r, P>, Dependency<P::Id>)>) {
+    let (pkg, platform) = unit;
+    let mut features = pkg.features();
+    let mut names = vec![name.to_string()];
+
+    while let Some(name) = names.pop() {
+        let known = features.contains_key(&name);
+        if !known || !enabled.entry((pkg.id(), platform)).or_default().insert(name.clone()) {
+            continue;
+        }
+
+        let feature = features.remove(&name).unwrap();
+        pending.extend(feature.dependencies.into_iter().rev().map(|dep| (unit, dep)));
+        names.extend(feature.enables.into_iter().rev());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+
+    use package::dependency::Dependency;
+    use super::*;
+
+    /// Ident conflicting with other versions of the same name
+    #[derive(Clone, Debug, Eq, Hash, PartialEq)]
+    struct MockIdent {
+        name: &'static str,
+        version: u32
+    }
+
+    impl fmt::Display for MockIdent {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{} {}", self.name, self.version)
+        }
+    }
+
+    impl Ident for MockIdent {
+        fn are_conflicting(instances: &[Self]) -> bool {
+            let mut found = HashSet::new();
+
+            !instances
+                .iter()
+                .all(move |x| found.insert(x.name))
+        }
+    }
+
+    #[derive(Clone, Debug, Eq, Hash, PartialEq)]
+    struct MockPackage {
+        id: MockIdent,
+        dependencies: Vec<Dependency<MockIdent>>
+    }
+
+    impl Package for MockPackage {
+        type Id = MockIdent;
+
+        fn id(&self) -> Self::Id {
+            self.id.clone()
+        }
+
+        fn dependencies(&self) -> Vec<Dependency<Self::Id>> {
+            self.dependencies.clone()
+        }
+    }
+
+    fn id(name: &'static str, version: u32) -> MockIdent {
+        MockIdent { name, version }
+    }
+
+    fn pkg(id: MockIdent, dependencies: Vec<Vec<MockIdent>>) -> MockPackage {
+        MockPackage {
+            id,
+            dependencies: dependencies
+                .into_iter()
+                .map(Dependency::Choice)
+                .collect()
+        }
+    }
+
+    #[test]
+    fn skips_missing_alternatives() {
+        // a -> b | c, b is not in the repository
+        let repo = Repository::new(vec![
+            pkg(id("a", 1), vec![vec![id("b", 1), id("c", 1)]]),
+            pkg(id("c", 1), vec![])]);
+
+        assert_eq!(
+            solve(&repo, &id("a", 1)),
+            Some(vec![id("a", 1), id("c", 1)]));
+    }
+
+    #[test]
+    fn selects_compatible_versions() {
+        // a -> x1 | x2, a -> y, y -> x2
+        let repo = Repository::new(vec![
+            pkg(id("a", 1), vec![vec![id("x", 1), id("x", 2)], vec![id("y", 1)]]),
+            pkg(id("x", 1), vec![]),
+            pkg(id("x", 2), vec![]),
+            pkg(id("y", 1), vec![vec![id("x", 2)]])]);
+
+        assert_eq!(
+            solve(&repo, &id("a", 1)),
+            Some(vec![id("a", 1), id("x", 2), id("y", 1)]));
+    }
+
+    #[test]
+    fn detects_conflicts() {
+        // a -> x1, a -> y, y -> x2
+        let repo = Repository::new(vec![
+            pkg(id("a", 1), vec![vec![id("x", 1)], vec![id("y", 1)]]),
+            pkg(id("x", 1), vec![]),
+            pkg(id("x", 2), vec![]),
+            pkg(id("y", 1), vec![vec![id("x", 2)]])]);
+
+        assert_eq!(solve(&repo, &id("a", 1)), None);
+    }
+
+    #[test]
+    fn shares_dependencies() {
+        // a -> b, a -> c, b -> d, c -> d
+        let repo = Repository::new(vec![
+            pkg(id("a", 1), vec![vec![id("b", 1)], vec![id("c", 1)]]),
+            pkg(id("b", 1), vec![vec![id("d", 1)]]),
+            pkg(id("c", 1), vec![vec![id("d", 1)]]),
+            pkg(id("d", 1), vec![])]);
+
+        assert_eq!(
+            solve(&repo, &id("a", 1)),
+            Some(vec![id("a", 1), id("b", 1), id("d", 1), id("c", 1)]));
+    }
+
+    #[test]
+    fn resolves_build_dependencies_on_host() {
+        // a -> x1, a -> x2 to build, a -> t for development, t -> x2,
+        // x1 -> z for development, x2 -> y to build, z is not in the
+        // repository
+        let scoped = |ids: Vec<MockIdent>, scope| Dependency::Choice(ids).in_scope(scope);
+        let repo = Repository::new(vec![
+            MockPackage {
+                id: id("a", 1),
+                dependencies: vec![
+                    Dependency::Choice(vec![id("x", 1)]),
+                    scoped(vec![id("x", 2)], Scope::Build),
+                    scoped(vec![id("t", 1)], Scope::Dev)]
+            },
+            MockPackage { id: id("x", 1), dependencies: vec![scoped(vec![id("z", 1)], Scope::Dev)] },
+            MockPackage { id: id("x", 2), dependencies: vec![scoped(vec![id("y", 1)], Scope::Build)] },
+            pkg(id("t", 1), vec![vec![id("x", 2)]]),
+            pkg(id("y", 1), vec![])]);
+
+        let selection = select(&repo, &id("a", 1)).unwrap();
+        assert_eq!(selection.ids, vec![id("a", 1), id("x", 1)]);
+        assert_eq!(selection.host, vec![id("x", 2), id("y", 1)]);
+
+        let runtime = select_for(&repo, &id("a", 1), &Request::new().without_scope(Scope::Build)).unwrap();
+        assert_eq!(runtime.ids, vec![id("a", 1), id("x", 1)]);
+        assert!(runtime.host.is_empty());
+
+        assert_eq!(select_for(&repo, &id("a", 1), &Request::new().with_scope(Scope::Dev)), None);
+    }
+
+    #[test]
+    fn explains_conflicts_on_host() {
+        // a -> x1 and y to build, y -> x2
+        let scoped = |ids: Vec<MockIdent>| Dependency::Choice(ids).in_scope(Scope::Build);
+        let a = MockPackage {
+            id: id("a", 1),
+            dependencies: vec![scoped(vec![id("x", 1)]), scoped(vec![id("y", 1)])]
+        };
+        let repo = Repository::new(vec![
+            a.clone(),
+            pkg(id("x", 1), vec![]),
+            pkg(id("x", 2), vec![]),
+            pkg(id("y", 1), vec![vec![id("x", 2)]])]);
+
+        assert_eq!(
+            explain(&repo, &id("a", 1)).unwrap().unwrap().to_string(),
+            "1. a 1 is requested\n\
+             2. a 1 depends on x 1\n\
+             3. a 1 depends on y 1\n\
+             4. y 1 depends on x 2\n\
+             5. x 2 conflicts with x 1 on the host\n\
+             6. Because of 1, 2, 3, 4 and 5, there is no solution\n");
+
+        // Without x2, y recommends w instead, which is not in the repository
+        let y = MockPackage { id: id("y", 1), dependencies: vec![Dependency::recommends(Dependency::Choice(vec![id("w", 1)]))] };
+        let repo = Repository::new(vec![a, pkg(id("x", 1), vec![]), y.clone()]);
+        let selection = select(&repo, &id("a", 1)).unwrap();
+        assert_eq!(selection.skipped, vec![(id("y", 1), Platform::Host, y.dependencies[0].clone())]);
+    }
+
+    #[test]
+    fn explains_conflicts() {
+        // a -> x1 | c, a -> y, y -> x2, c is not in the repository
+        let repo = Repository::new(vec![
+            pkg(id("a", 1), vec![vec![id("x", 1), id("c", 1)], vec![id("y", 1)]]),
+            pkg(id("x", 1), vec![]),
+            pkg(id("x", 2), vec![]),
+            pkg(id("y", 1), vec![vec![id("x", 2)]])]);
+
+        assert_eq!(explain(&repo, &id("y", 1)), Ok(None));
+
+        let explanation = explain(&repo, &id("a", 1)).unwrap().unwrap();
+
+        assert_eq!(
+            explanation.to_string(),
+            "1. a 1 is requested\n\
+             2. a 1 depends on x 1 or c 1\n\
+             3. c 1 is not in the repository\n\
+             4. a 1 depends on y 1\n\
+             5. y 1 depends on x 2\n\
+             6. x 2 conflicts with x 1\n\
+             7. Because of 1, 2, 3, 4, 5 and 6, there is no solution\n");
+    }
+
+    #[test]
+    fn respects_declared_conflicts() {
+        // a -> b | c, a -> d, d conflicts with b
+        let d = MockPackage {
+            id: id("d", 1),
+            dependencies: vec![Dependency::conflicts(Dependency::Choice(vec![id("b", 1)]))]
+        };
+        let repo = Repository::new(vec![
+            pkg(id("a", 1), vec![vec![id("b", 1), id("c", 1)], vec![id("d", 1)]]),
+            pkg(id("b", 1), vec![]),
+            pkg(id("c", 1), vec![]),
+            d]);
+
+        assert_eq!(
+            solve(&repo, &id("a", 1)),
+            Some(vec![id("a", 1), id("c", 1), id("d", 1)]));
+    }
+
+    #[test]
+    fn satisfies_weak_dependencies() {
+        // a -> d, a recommends b, a suggests x2, a recommends e, d -> x1,
+        // b suggests f, e is not in the repository
+        let (rec, sug) = (Dependency::recommends, Dependency::suggests);
+        let a = MockPackage {
+            id: id("a", 1),
+            dependencies: vec![
+                Dependency::Choice(vec![id("d", 1)]),
+                rec(Dependency::Choice(vec![id("b", 1)])),
+                sug(Dependency::Choice(vec![id("x", 2)])),
+                rec(Dependency::Choice(vec![id("e", 1)]))]
+        };
+        let b = MockPackage {
+            id: id("b", 1),
+            dependencies: vec![sug(Dependency::Choice(vec![id("f", 1)]))]
+        };
+        let repo = Repository::new(vec![
+            a.clone(),
+            b.clone(),
+            pkg(id("d", 1), vec![vec![id("x", 1)]]),
+            pkg(id("x", 1), vec![]),
+            pkg(id("x", 2), vec![]),
+            pkg(id("f", 1), vec![])]);
+
+        assert_eq!(
+            select(&repo, &id("a", 1)),
+            Some(Selection {
+                ids: vec![id("a", 1), id("d", 1), id("x", 1), id("b", 1), id("f", 1)],
+                host: vec![],
+                satisfied: vec![
+                    (id("a", 1), Platform::Target, a.dependencies[1].clone()),
+                    (id("b", 1), Platform::Target, b.dependencies[0].clone())],
+                skipped: vec![
+                    (id("a", 1), Platform::Target, a.dependencies[2].clone()),
+                    (id("a", 1), Platform::Target, a.dependencies[3].clone())],
+                features: ["a", "d", "x", "b", "f"]
+                    .iter()
+                    .map(|&name| (id(name, 1), BTreeSet::new()))
+                    .collect(),
+                host_features: HashMap::new()
+            }));
+    }
+
+    #[test]
+    fn prefers_recommendations() {
+        // a suggests x2, a recommends y, y -> x1
+        let a = MockPackage {
+            id: id("a", 1),
+            dependencies: vec![
+                Dependency::suggests(Dependency::Choice(vec![id("x", 2)])),
+                Dependency::recommends(Dependency::Choice(vec![id("y", 1)]))]
+        };
+        let repo = Repository::new(vec![
+            a.clone(),
+            pkg(id("x", 1), vec![]),
+            pkg(id("x", 2), vec![]),
+            pkg(id("y", 1), vec![vec![id("x", 1)]])]);
+
+        let selection = select(&repo, &id("a", 1)).unwrap();
+
+        assert_eq!(selection.ids, vec![id("a", 1), id("y", 1), id("x", 1)]);
+        assert_eq!(selection.skipped, vec![(id("a", 1), Platform::Target, a.dependencies[0].clone())]);
+    }
+
+    #[test]
+    fn explains_breaks() {
+        // a -> b, a -> c, b breaks c
+        let b = MockPackage {
+            id: id("b", 1),
+            dependencies: vec![Dependency::breaks(Dependency::Choice(vec![id("c", 1)]))]
+        };
+        let repo = Repository::new(vec![
+            pkg(id("a", 1), vec![vec![id("b", 1)], vec![id("c", 1)]]),
+            b,
+            pkg(id("c", 1), vec![])]);
+
+        assert_eq!(
+            explain(&repo, &id("a", 1)).unwrap().unwrap().to_string(),
+            "1. a 1 is requested\n\
+             2. a 1 depends on b 1\n\
+             3. a 1 depends on c 1\n\
+             4. b 1 breaks c 1\n\
+             5. Because of 1, 2, 3 and 4, there is no solution\n");
+    }
+
+    #[test]
+    fn explains_unmatched_ranges() {
+        let a = MockPackage {
+            id: id("a", 1),
+            dependencies: vec![Dependency::range("x", ">=2".parse().unwrap())]
+        };
+        let repo = Repository::new(vec![a, pkg(id("x", 1), vec![])]);
+
+        assert_eq!(
+            explain(&repo, &id("a", 1)).unwrap().unwrap().to_string(),
+            "1. a 1 is requested\n\
+             2. a 1 depends on x >=2, which nothing matches\n\
+             3. Because of 1 and 2, there is no solution\n");
+    }
+
+    #[test]
+    fn explains_missing_root() {
+        let repo = Repository::new(vec![pkg(id("a", 1), vec![])]);
+
+        assert_eq!(
+            explain(&repo, &id("b", 1)).unwrap().unwrap().to_string(),
+            "1. b 1 is not in the repository\n\
+             2. Because of 1, there is no solution\n");
+    }
+
+    #[test]
+    fn requires_root() {
+        let repo = Repository::new(vec![pkg(id("a", 1), vec![])]);
+
+        assert_eq!(solve(&repo, &id("b", 1)), None);
+    }
+}
@@ -0,0 +1,717 @@
+use std::mem;
+
+use super::cnf::Clause;
+use super::cnf::Cnf;
+use super::cnf::Literal;
+use super::cnf::Variable;
+
+/// Step of a proof of unsatisfiability.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Step {
+    /// Clause of the input, by the order it was added in
+    Input(usize),
+    /// Clause implied by earlier steps, given by their positions
+    Derived(Clause, Vec<usize>)
+}
+
+/// Conflict-driven clause learning SAT solver.
+///
+/// Uses two watched literals per clause, first-UIP learning with
+/// non-chronological backjumping, activity based branching and restarts.
+/// Branches on `false` first, so models tend to be small.
+///
+/// Learnt clauses remember the clauses they were derived from, so that
+/// a failed `solve` can be justified with a `refutation`.
+#[derive(Clone, Debug)]
+pub struct Solver {
+    clauses: Vec<Clause>,
+    /// Position in the input of each clause, `None` for learnt ones
+    inputs: Vec<Option<usize>>,
+    /// Clauses each learnt clause was derived from
+    antecedents: Vec<Vec<usize>>,
+    /// Clauses watching each literal, by literal index
+    watches: Vec<Vec<usize>>,
+    /// Single literal clauses
+    units: Vec<usize>,
+    added: usize,
+    /// Clauses the empty clause follows from, once found
+    refuted: Option<Vec<usize>>,
+
+    assignment: Vec<Option<bool>>,
+    level: Vec<usize>,
+    reason: Vec<Option<usize>>,
+    activity: Vec<f64>,
+    increment: f64,
+
+    trail: Vec<Literal>,
+    /// Trail length at the start of each decision level
+    trail_lim: Vec<usize>,
+    /// Next trail position to propagate
+    head: usize,
+
+    conflicts: usize
+}
+
+const DECAY: f64 = 0.95;
+const RESTART_FIRST: usize = 100;
+const RESTART_GROWTH: f64 = 1.5;
+
+impl Solver {
+    pub fn new(cnf: &Cnf) -> Self {
+        let variables = cnf.variables;
+
+        let mut solver = Solver {
+            clauses: Vec::new(),
+            inputs: Vec::new(),
+            antecedents: Vec::new(),
+            watches: vec![Vec::new(); variables * 2],
+            units: Vec::new(),
+            added: 0,
+            refuted: None,
+            assignment: vec![None; variables],
+            level: vec![0; variables],
+            reason: vec![None; variables],
+            activity: vec![0.0; variables],
+            increment: 1.0,
+            trail: Vec::new(),
+            trail_lim: Vec::new(),
+            head: 0,
+            conflicts: 0
+        };
+
+        for clause in &cnf.clauses {
+            solver.add_clause(clause.clone());
+        }
+
+        solver
+    }
+
+    /// Number of conflicts met so far, over all calls.
+    pub fn conflicts(&self) -> usize {
+        self.conflicts
+    }
+
+    pub fn add_clause(&mut self, mut clause: Clause) {
+        self.backtrack(0);
+
+        let input = self.added;
+        self.added += 1;
+
+        clause.sort();
+        clause.dedup();
+
+        let tautology = clause
+            .windows(2)
+            .any(|pair| pair[0] == !pair[1]);
+
+        if !tautology {
+            let index = self.store(clause, Some(input), vec![]);
+
+            if self.clauses[index].is_empty() {
+                self.refuted = Some(vec![index]);
+            }
+        }
+    }
+
+    /// Proof that the clauses have no model, after `solve` failed without
+    /// assumptions. The last step derives the empty clause.
+    pub fn refutation(&self) -> Option<Vec<Step>> {
+        let roots = self.refuted.as_ref()?;
+
+        // Single input clause: it's empty
+        if let [index] = roots[..] {
+            if let Some(input) = self.inputs[index] {
+                return Some(vec![Step::Input(input)]);
+            }
+        }
+
+        let mut steps = vec![];
+        let mut positions = vec![None; self.clauses.len()];
+
+        for &root in roots {
+            self.collect(root, &mut positions, &mut steps);
+        }
+
+        let mut because: Vec<usize> = roots
+            .iter()
+            .map(|&root| positions[root].unwrap())
+            .collect();
+        because.sort();
+        because.dedup();
+
+        steps.push(Step::Derived(vec![], because));
+        Some(steps)
+    }
+
+    /// Adds the derivation of `index` to `steps`, antecedents first.
+    fn collect(&self, index: usize, positions: &mut [Option<usize>], steps: &mut Vec<Step>) {
+        let mut pending = vec![(index, false)];
+
+        while let Some((index, expanded)) = pending.pop() {
+            if positions[index].is_some() {
+                continue;
+            }
+
+            if expanded || self.inputs[index].is_some() {
+                let step = match self.inputs[index] {
+                    Some(input) => Step::Input(input),
+                    None => Step::Derived(
+                        self.clauses[index].clone(),
+                        self.antecedents[index]
+                            .iter()
+                            .map(|&a| positions[a].unwrap())
+                            .collect())
+                };
+                positions[index] = Some(steps.len());
+                steps.push(step);
+            } else {
+                pending.push((index, true));
+                pending.extend(
+                    self.antecedents[index]
+                        .iter()
+                        .map(|&a| (a, false)));
+            }
+        }
+    }
+
+    fn store(&mut self, clause: Clause, input: Option<usize>, antecedents: Vec<usize>) -> usize {
+        let index = self.clauses.len();
+
+        match clause.len() {
+            0 => {},
+            1 => self.units.push(index),
+            _ => self.watch(&clause, index)
+        }
+
+        self.clauses.push(clause);
+        self.inputs.push(input);
+        self.antecedents.push(antecedents);
+        index
+    }
+
+    /// Finds a model, with one value per variable.
+    pub fn solve(&mut self) -> Option<Vec<bool>> {
+        self.solve_with(&[])
+    }
+
+    /// Finds a model in which all `assumptions` hold.
+    pub fn solve_with(&mut self, assumptions: &[Literal]) -> Option<Vec<bool>> {
+        if self.refuted.is_some() {
+            return None;
+        }
+
+        self.backtrack(0);
+
+        for unit in self.units.clone() {
+            let lit = self.clauses[unit][0];
+            match self.value(lit) {
+                Some(true) => {},
+                Some(false) => {
+                    self.refute(unit);
+                    return None;
+                },
+                None => self.enqueue(lit, Some(unit))
+            }
+        }
+
+        let mut restart_limit = RESTART_FIRST as f64;
+        let mut restart_conflicts = 0;
+
+        loop {
+            if let Some(conflict) = self.propagate() {
+                self.conflicts += 1;
+                restart_conflicts += 1;
+
+                if self.decision_level() == 0 {
+                    self.refute(conflict);
+                    return None;
+                }
+
+                let (learnt, antecedents, level) = self.analyze(conflict);
+                self.backtrack(level);
+                self.learn(learnt, antecedents);
+                self.decay();
+                continue;
+            }
+
+            if restart_conflicts as f64 >= restart_limit {
+                restart_conflicts = 0;
+                restart_limit *= RESTART_GROWTH;
+                self.backtrack(0);
+                continue;
+            }
+
+            // Assumptions are the first decisions
+            let level = self.decision_level();
+            if level < assumptions.len() {
+                let lit = assumptions[level];
+                match self.value(lit) {
+                    Some(true) => self.trail_lim.push(self.trail.len()),
+                    Some(false) => return None,
+                    None => {
+                        self.trail_lim.push(self.trail.len());
+                        self.enqueue(lit, None);
+                    }
+                }
+                continue;
+            }
+
+            match self.pick_branch() {
+                Some(variable) => {
+                    self.trail_lim.push(self.trail.len());
+                    self.enqueue(Literal::negative(variable), None);
+                },
+                None => {
+                    let model = self.assignment
+                        .iter()
+                        .map(|value| value.unwrap_or(false))
+                        .collect();
+                    return Some(model);
+                }
+            }
+        }
+    }
+
+    fn watch(&mut self, clause: &[Literal], index: usize) {
+        self.watches[clause[0].index()].push(index);
+        self.watches[clause[1].index()].push(index);
+    }
+
+    fn value(&self, lit: Literal) -> Option<bool> {
+        self.assignment[lit.variable()].map(|value| value != lit.is_negative())
+    }
+
+    fn decision_level(&self) -> usize {
+        self.trail_lim.len()
+    }
+
+    fn enqueue(&mut self, lit: Literal, reason: Option<usize>) {
+        let variable = lit.variable();
+        self.assignment[variable] = Some(!lit.is_negative());
+        self.level[variable] = self.decision_level();
+        self.reason[variable] = reason;
+        self.trail.push(lit);
+    }
+
+    /// Propagates all pending assignments, returning a falsified clause
+    /// if any.
+    fn propagate(&mut self) -> Option<usize> {
+        while self.head < self.trail.len() {
+            let falsified = !self.trail[self.head];
+            self.head += 1;
+
+            let mut watching = mem::take(&mut self.watches[falsified.index()]);
+            let mut conflict = None;
+            let mut kept = 0;
+            let mut i = 0;
+
+            while i < watching.len() {
+                let index = watching[i];
+                i += 1;
+
+                // Keep the falsified watch at position 1
+                if self.clauses[index][0] == falsified {
+                    self.clauses[index].swap(0, 1);
+                }
+
+                let first = self.clauses[index][0];
+                if self.value(first) == Some(true) {
+                    watching[kept] = index;
+                    kept += 1;
+                    continue;
+                }
+
+                let replacement = (2..self.clauses[index].len())
+                    .find(|&k| self.value(self.clauses[index][k]) != Some(false));
+
+                match replacement {
+                    Some(k) => {
+                        self.clauses[index].swap(1, k);
+                        let lit = self.clauses[index][1];
+                        self.watches[lit.index()].push(index);
+                    },
+                    None => {
+                        watching[kept] = index;
+                        kept += 1;
+
+                        if self.value(first) == Some(false) {
+                            conflict = Some(index);
+                            break;
+                        }
+                        self.enqueue(first, Some(index));
+                    }
+                }
+            }
+
+            // Keep the watches not visited because of a conflict
+            while i < watching.len() {
+                watching[kept] = watching[i];
+                kept += 1;
+                i += 1;
+            }
+            watching.truncate(kept);
+            self.watches[falsified.index()] = watching;
+
+            if conflict.is_some() {
+                return conflict;
+            }
+        }
+
+        None
+    }
+
+    /// Records the derivation of the empty clause from a clause falsified
+    /// at level 0.
+    fn refute(&mut self, conflict: usize) {
+        let mut antecedents = vec![conflict];
+        self.facts(conflict, &mut antecedents);
+        antecedents.sort();
+        antecedents.dedup();
+
+        self.refuted = Some(antecedents);
+    }
+
+    /// Adds the reasons of the level 0 literals of `clause` to
+    /// `antecedents`, along with the reasons they follow from.
+    fn facts(&self, clause: usize, antecedents: &mut Vec<usize>) {
+        let mut seen = vec![false; self.assignment.len()];
+        let mut pending = vec![clause];
+
+        while let Some(clause) = pending.pop() {
+            for lit in self.clauses[clause].iter() {
+                let variable = lit.variable();
+                if seen[variable] || self.level[variable] != 0 {
+                    continue;
+                }
+                seen[variable] = true;
+
+                if let Some(reason) = self.reason[variable] {
+                    antecedents.push(reason);
+                    pending.push(reason);
+                }
+            }
+        }
+    }
+
+    /// Derives the first-UIP clause of a conflict, the clauses it follows
+    /// from and the level to jump back to. The asserting literal is first,
+    /// the highest remaining one second.
+    fn analyze(&mut self, conflict: usize) -> (Clause, Vec<usize>, usize) {
+        let level = self.decision_level();
+        let mut seen = vec![false; self.assignment.len()];
+        let mut learnt = vec![];
+        let mut antecedents = vec![];
+        let mut pending = 0;
+        let mut index = self.trail.len();
+        let mut clause = conflict;
+        let mut implied: Option<Literal> = None;
+
+        loop {
+            antecedents.push(clause);
+
+            for k in 0..self.clauses[clause].len() {
+                let lit = self.clauses[clause][k];
+                if Some(lit) == implied {
+                    continue;
+                }
+
+                let variable = lit.variable();
+
+                if !seen[variable] && self.level[variable] > 0 {
+                    seen[variable] = true;
+                    self.bump(variable);
+
+                    if self.level[variable] == level {
+                        pending += 1;
+                    } else {
+                        learnt.push(lit);
+                    }
+                }
+            }
+
+            // Walk back to the next literal of this level taking part
+            loop {
+                index -= 1;
+                if seen[self.trail[index].variable()] {
+                    break;
+                }
+            }
+
+            let lit = self.trail[index];
+            seen[lit.variable()] = false;
+            pending -= 1;
+
+            if pending == 0 {
+                learnt.insert(0, !lit);
+                break;
+            }
+
+            implied = Some(lit);
+            clause = self.reason[lit.variable()].expect("implied literal without a reason");
+        }
+
+        // Facts known at level 0 are resolved away silently
+        for &clause in antecedents.clone().iter() {
+            self.facts(clause, &mut antecedents);
+        }
+
+        let backjump = match learnt.len() {
+            1 => 0,
+            _ => {
+                let (position, _) = learnt
+                    .iter()
+                    .enumerate()
+                    .skip(1)
+                    .max_by_key(|&(_, lit)| self.level[lit.variable()])
+                    .unwrap();
+                learnt.swap(1, position);
+                self.level[learnt[1].variable()]
+            }
+        };
+
+        antecedents.sort();
+        antecedents.dedup();
+
+        (learnt, antecedents, backjump)
+    }
+
+    fn learn(&mut self, learnt: Clause, antecedents: Vec<usize>) {
+        let asserting = learnt[0];
+        let index = self.store(learnt, None, antecedents);
+        self.enqueue(asserting, Some(index));
+    }
+
+    fn backtrack(&mut self, level: usize) {
+        if self.decision_level() <= level {
+            return;
+        }
+
+        let start = self.trail_lim[level];
+        for lit in self.trail.drain(start..) {
+            let variable = lit.variable();
+            self.assignment[variable] = None;
+            self.reason[variable] = None;
+        }
+
+        self.trail_lim.truncate(level);
+        self.head = self.trail.len();
+    }
+
+    fn pick_branch(&self) -> Option<Variable> {
+        let mut best: Option<Variable> = None;
+
+        for variable in 0..self.assignment.len() {
+            if self.assignment[variable].is_none() {
+                let better = match best {
+                    Some(b) => self.activity[variable] > self.activity[b],
+                    None => true
+                };
+                if better {
+                    best = Some(variable);
+                }
+            }
+        }
+
+        best
+    }
+
+    fn bump(&mut self, variable: Variable) {
+        self.activity[variable] += self.increment;
+
+        if self.activity[variable] > 1e100 {
+            for activity in self.activity.iter_mut() {
+                *activity *= 1e-100;
+            }
+            self.increment *= 1e-100;
+        }
+    }
+
+    fn decay(&mut self) {
+        self.increment /= DECAY;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pos(variable: Variable) -> Literal {
+        Literal::positive(variable)
+    }
+
+    fn neg(variable: Variable) -> Literal {
+        Literal::negative(variable)
+    }
+
+    /// `pigeons` pigeons in `holes` holes, each pigeon in some hole
+    /// and no two in the same one.
+    fn pigeonhole(pigeons: usize, holes: usize) -> Cnf {
+        let mut cnf = Cnf::new();
+        let var = |pigeon: usize, hole: usize| pigeon * holes + hole;
+
+        for _ in 0..(pigeons * holes) {
+            cnf.variable();
+        }
+
+        for pigeon in 0..pigeons {
+            cnf.add((0..holes).map(|hole| pos(var(pigeon, hole))).collect());
+        }
+
+        for hole in 0..holes {
+            for a in 0..pigeons {
+                for b in (a + 1)..pigeons {
+                    cnf.add(vec![neg(var(a, hole)), neg(var(b, hole))]);
+                }
+            }
+        }
+
+        cnf
+    }
+
+    /// Deterministic random 3-SAT instance.
+    fn random_3sat(variables: usize, clauses: usize, mut seed: u64) -> Cnf {
+        let mut cnf = Cnf::new();
+        for _ in 0..variables {
+            cnf.variable();
+        }
+
+        let mut next = || {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (seed >> 33) as usize
+        };
+
+        for _ in 0..clauses {
+            let clause = (0..3)
+                .map(|_| {
+                    let variable = next() % variables;
+                    match next() % 2 {
+                        0 => pos(variable),
+                        _ => neg(variable)
+                    }
+                })
+                .collect();
+            cnf.add(clause);
+        }
+
+        cnf
+    }
+
+    #[test]
+    fn solves_trivial() {
+        let mut cnf = Cnf::new();
+        let (a, b) = (cnf.variable(), cnf.variable());
+
+        cnf.add(vec![pos(a), pos(b)]);
+        cnf.add(vec![neg(a)]);
+
+        assert_eq!(Solver::new(&cnf).solve(), Some(vec![false, true]));
+    }
+
+    #[test]
+    fn detects_empty_clause() {
+        let mut cnf = Cnf::new();
+        cnf.variable();
+        cnf.add(vec![]);
+
+        assert_eq!(Solver::new(&cnf).solve(), None);
+    }
+
+    #[test]
+    fn refutes_pigeonhole() {
+        assert_eq!(Solver::new(&pigeonhole(5, 4)).solve(), None);
+
+        let cnf = pigeonhole(4, 4);
+        let model = Solver::new(&cnf).solve().unwrap();
+        assert!(cnf.satisfied_by(&model));
+    }
+
+    /// Whether unit propagation over `antecedents` refutes the negation
+    /// of `clause`.
+    fn propagates_to_conflict(clause: &[Literal], antecedents: &[&Clause]) -> bool {
+        let mut assigned: Vec<Literal> = clause.iter().map(|&lit| !lit).collect();
+
+        loop {
+            let mut progress = false;
+
+            for antecedent in antecedents {
+                let open: Vec<_> = antecedent
+                    .iter()
+                    .filter(|&&lit| !assigned.contains(&!lit))
+                    .collect();
+
+                if open.iter().any(|&&lit| assigned.contains(&lit)) {
+                    continue;
+                }
+
+                match open[..] {
+                    [] => return true,
+                    [&unit] => { assigned.push(unit); progress = true; },
+                    _ => {}
+                }
+            }
+
+            if !progress {
+                return false;
+            }
+        }
+    }
+
+    #[test]
+    fn logs_refutation() {
+        let cnf = pigeonhole(5, 4);
+        let mut solver = Solver::new(&cnf);
+        assert_eq!(solver.solve(), None);
+
+        let steps = solver.refutation().unwrap();
+        let mut derived: Vec<Clause> = vec![];
+
+        for step in steps.iter() {
+            let clause = match step {
+                Step::Input(input) => cnf.clauses[*input].clone(),
+                Step::Derived(clause, because) => {
+                    assert!(because.iter().all(|&i| i < derived.len()));
+                    let antecedents: Vec<_> = because.iter().map(|&i| &derived[i]).collect();
+                    assert!(propagates_to_conflict(clause, &antecedents));
+                    clause.clone()
+                }
+            };
+            derived.push(clause);
+        }
+
+        assert_eq!(derived.last(), Some(&vec![]));
+    }
+
+    #[test]
+    fn solves_random_instances() {
+        let mut solved = 0;
+
+        for seed in 0..50 {
+            let cnf = random_3sat(30, 128, seed);
+
+            if let Some(model) = Solver::new(&cnf).solve() {
+                assert!(cnf.satisfied_by(&model));
+                solved += 1;
+            }
+        }
+
+        // Around the phase transition, so both outcomes show up
+        assert!(solved > 0 && solved < 50);
+    }
+
+    #[test]
+    fn solves_under_assumptions() {
+        let mut cnf = Cnf::new();
+        let (a, b) = (cnf.variable(), cnf.variable());
+
+        // a -> b
+        cnf.add(vec![neg(a), pos(b)]);
+
+        let mut solver = Solver::new(&cnf);
+
+        assert_eq!(solver.solve_with(&[pos(a)]), Some(vec![true, true]));
+        assert_eq!(solver.solve_with(&[pos(a), neg(b)]), None);
+
+        // Failed assumptions don't stick
+        assert_eq!(solver.solve(), Some(vec![false, false]));
+    }
+}
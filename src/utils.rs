@@ -1,22 +1,59 @@
-pub fn selections<T: Clone>(mut sources: Vec<Vec<T>>) -> Vec<Vec<T>> {
-    if sources.len() == 0 {
-        vec![vec![]]
-    } else {
-        let head = sources.remove(0);
-        // sources is 1-element shorter now
-        let subresults = selections(sources);
-
-        let mut ans: Vec<Vec<T>> = Vec::new();
-
-        for head_element in head.iter() {
-            for result in subresults.iter() {
-                let mut selection = vec![head_element.clone()];
-                selection.extend(result.clone());
-                ans.push(selection);
+/// Cartesian product of `sources`, picking one element of each.
+pub fn selections<T: Clone>(sources: Vec<Vec<T>>) -> Vec<Vec<T>> {
+    Selections::new(sources).collect()
+}
+
+/// Lazy version of `selections`, producing one selection at a time.
+#[derive(Clone, Debug)]
+pub struct Selections<T: Clone> {
+    sources: Vec<Vec<T>>,
+    indices: Vec<usize>,
+    done: bool
+}
+
+impl<T: Clone> Selections<T> {
+    pub fn new(sources: Vec<Vec<T>>) -> Self {
+        let done = sources.iter().any(|source| source.is_empty());
+
+        Selections {
+            indices: vec![0; sources.len()],
+            sources,
+            done
+        }
+    }
+
+    /// Moves to the next combination, last source first.
+    fn advance(&mut self) {
+        for (index, source) in self.indices.iter_mut().zip(self.sources.iter()).rev() {
+            *index += 1;
+            if *index < source.len() {
+                return;
             }
+            *index = 0;
+        }
+
+        // Every index wrapped around
+        self.done = true;
+    }
+}
+
+impl<T: Clone> Iterator for Selections<T> {
+    type Item = Vec<T>;
+
+    fn next(&mut self) -> Option<Vec<T>> {
+        if self.done {
+            return None;
         }
 
-        ans
+        let selection = self.indices
+            .iter()
+            .zip(self.sources.iter())
+            .map(|(&index, source)| source[index].clone())
+            .collect();
+
+        self.advance();
+
+        Some(selection)
     }
 }
 
@@ -52,5 +89,21 @@ mod tests {
             vec![vec![1, 2], vec![3], vec![4]],
             vec![vec![1, 3, 4], vec![2, 3, 4]]
         );
+
+        assert_selects(
+            vec![vec![1, 2], vec![], vec![4]],
+            vec![]
+        );
+    }
+
+    #[test]
+    fn selects_lazily() {
+        let mut lazy = Selections::new(vec![vec![1, 2], vec![3, 4]]);
+
+        assert_eq!(lazy.next(), Some(vec![1, 3]));
+        assert_eq!(lazy.next(), Some(vec![1, 4]));
+
+        let tail: Vec<_> = lazy.collect();
+        assert_eq!(tail, vec![vec![2, 3], vec![2, 4]]);
     }
 }
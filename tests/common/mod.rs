@@ -0,0 +1,43 @@
+// Not every test crate uses every helper
+#![allow(dead_code)]
+
+use rosol::node::Node;
+use rosol::package::ident::SimpleUnique;
+use rosol::resolvables::AndDependency;
+use rosol::resolvables::OrAnd;
+use rosol::resolvables::OrDependency;
+
+/// Id of a generated name, leaked so that it lives as long as the tests.
+pub fn id(name: String) -> SimpleUnique {
+    SimpleUnique { id: Box::leak(name.into_boxed_str()) }
+}
+
+/// Pseudo-random graph of `size` nodes, drawing ids from a small pool so
+/// that some paths conflict.
+pub fn random_graph(size: usize, seed: u64) -> Node<OrAnd<SimpleUnique>> {
+    let mut state = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+    let mut random = |bound: usize| {
+        state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        ((state >> 33) % bound as u64) as usize
+    };
+
+    let mut nodes: Vec<Node<OrAnd<SimpleUnique>>> = vec![];
+
+    for _ in 0..size {
+        let id = id(format!("p{}", random(6)));
+
+        let dependency = match nodes.len() {
+            0 => None,
+            n => {
+                let alternatives = (0..random(3))
+                    .map(|_| AndDependency::new((0..random(2) + 1).map(|_| nodes[random(n)].clone()).collect()))
+                    .collect();
+                Some(OrAnd::new(OrDependency::new(alternatives)))
+            }
+        };
+
+        nodes.push(Node { id, dependency });
+    }
+
+    nodes.pop().unwrap()
+}
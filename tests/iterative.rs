@@ -0,0 +1,172 @@
+extern crate rosol;
+
+mod common;
+
+use std::fmt;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::ptr;
+
+use rosol::context::Context;
+use rosol::node::Node;
+use rosol::node::cause::Cause;
+use rosol::node::resolvable::Resolvable;
+use rosol::node::resolved::Resolved;
+use rosol::package::ident::SimpleUnique;
+use rosol::path::Path;
+
+use common::id;
+use common::random_graph;
+
+/// Dependency on a single node, compared by address so that long chains
+/// are hashed and dropped without recursion.
+#[derive(Clone)]
+struct Link {
+    next: &'static Node<Link>,
+    exclusions: Vec<SimpleUnique>
+}
+
+impl PartialEq for Link {
+    fn eq(&self, other: &Self) -> bool {
+        ptr::eq(self.next, other.next) && self.exclusions == other.exclusions
+    }
+}
+
+impl Eq for Link {}
+
+impl Hash for Link {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.next as *const Node<Link>).hash(state);
+        self.exclusions.hash(state);
+    }
+}
+
+impl fmt::Debug for Link {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Link({})", self.next.id.id)
+    }
+}
+
+impl Resolvable for Link {
+    type Id = SimpleUnique;
+
+    fn resolve<'a>(&'a self, path: Path<'a, Self>, context: &mut Context<'a, Self>) -> Resolved<'a, Self> {
+        self.next.solve_in(path, context)
+    }
+
+    fn alternatives(&self) -> Vec<Vec<&Node<Self>>> {
+        vec![vec![self.next]]
+    }
+
+    fn exclusions(&self) -> Vec<SimpleUnique> {
+        self.exclusions.clone()
+    }
+}
+
+/// n0 -> n1 -> ... -> n(length - 1) -> tail
+fn chain(length: usize, tail: Option<&'static Node<Link>>) -> &'static Node<Link> {
+    let mut next = tail;
+
+    for i in (0..length).rev() {
+        let node = Node {
+            id: id(format!("n{}", i)),
+            dependency: next.map(|next| Link { next, exclusions: vec![] })
+        };
+        next = Some(Box::leak(Box::new(node)));
+    }
+
+    next.unwrap()
+}
+
+#[test]
+fn matches_recursive_on_random_graphs() {
+    let mut solved = 0;
+
+    for seed in 0..300 {
+        let root = random_graph(9, seed);
+
+        let recursive = root.solve(Path::new(vec![]));
+        let iterative = root.solve_iteratively(Path::new(vec![]), &mut Context::new());
+
+        assert_eq!(iterative, recursive, "seed {}", seed);
+
+        if recursive.is_success() {
+            solved += 1;
+        }
+    }
+
+    // Both outcomes are covered
+    assert!(solved > 0 && solved < 300);
+}
+
+#[test]
+fn matches_recursive_causes() {
+    // Solving a node already on the path blames the node
+    let tail = chain(1, None);
+    let path = Path::new(vec![tail]);
+
+    let recursive = tail.solve(path.clone());
+    assert!(!recursive.cause.nodes.is_empty());
+    assert_eq!(tail.solve_iteratively(path, &mut Context::new()), recursive);
+
+    // A shorter chain failing at its end
+    let root = chain(200, Some(chain(3, None)));
+
+    let recursive = root.solve(Path::new(vec![]));
+    assert!(!recursive.is_success());
+    assert_eq!(root.solve_iteratively(Path::new(vec![]), &mut Context::new()), recursive);
+}
+
+#[test]
+fn solves_long_chain() {
+    let length = 100_000;
+    let root = chain(length, None);
+
+    let res = root.solve_iteratively(Path::new(vec![]), &mut Context::new());
+
+    assert_eq!(res.paths.len(), 1);
+    assert!(res.cause.nodes.is_empty());
+
+    let idents = res.paths[0].idents();
+    assert_eq!(idents.len(), length);
+    assert_eq!(idents[0].id, "n0");
+    assert_eq!(idents[length - 1].id, "n99999");
+}
+
+#[test]
+fn fails_long_chain() {
+    // The end of the chain conflicts with its start
+    let root = chain(100_000, Some(chain(1, None)));
+
+    let res = root.solve_iteratively(Path::new(vec![]), &mut Context::new());
+
+    assert_eq!(res, Resolved::failure(Cause::empty()));
+}
+
+#[test]
+fn checks_exclusions_along_long_chain() {
+    // root -> n0 -> ... -> n99999, the root excluding z
+    let root = |tail| Node {
+        id: SimpleUnique::new("root"),
+        dependency: Some(Link { next: chain(100_000, tail), exclusions: vec![SimpleUnique::new("z")] })
+    };
+
+    let free = root(None);
+    let res = free.solve_iteratively(Path::new(vec![]), &mut Context::new());
+    assert_eq!(res.paths.len(), 1);
+
+    let z: &'static Node<Link> = Box::leak(Box::new(Node { id: SimpleUnique::new("z"), dependency: None }));
+    let excluded = root(Some(z));
+    let res = excluded.solve_iteratively(Path::new(vec![]), &mut Context::new());
+    assert!(!res.is_success());
+}
+
+#[test]
+fn honours_limit() {
+    let root = random_graph(9, 7);
+
+    let limited = root.solve_iteratively(Path::new(vec![]), &mut Context::first_solution());
+    let recursive = root.solve_in(Path::new(vec![]), &mut Context::first_solution());
+
+    assert_eq!(limited, recursive);
+}
@@ -1,18 +1,39 @@
 extern crate rosol;
 
-mod resolvable_impl;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
 
+mod common;
+
+use rosol::budget::Budget;
+use rosol::budget::Reason;
+use rosol::Error;
+use rosol::context::Context;
 use rosol::node::Node;
 use rosol::node::cause::Cause;
+use rosol::node::outcome::Outcome;
 use rosol::node::resolved::Resolved;
+use rosol::observer::Event;
+use rosol::observer::Recorder;
 use rosol::package::ident::SimpleUnique;
 use rosol::path::Path;
+use rosol::ranking::FewestChanges;
+use rosol::ranking::FewestPackages;
+use rosol::ranking::Lexicographic;
+
+use rosol::resolvables::AndDependency;
+use rosol::resolvables::Any;
+use rosol::resolvables::OrAnd;
+use rosol::resolvables::OrDependency;
+use rosol::resolvables::Simple;
 
-use self::resolvable_impl as resolvable;
+use common::id;
 
 #[test]
 fn single_node() {
-    type R = resolvable::Simple;
+    type R = Simple<SimpleUnique>;
 
     let id = SimpleUnique { id: "id1" };
 
@@ -32,7 +53,7 @@ fn single_node() {
 
 #[test]
 fn duplicate() {
-    type R = resolvable::Simple;
+    type R = Simple<SimpleUnique>;
 
     let id = SimpleUnique { id: "id1" };
 
@@ -52,7 +73,7 @@ fn duplicate() {
 
 #[test]
 fn circular() {
-    type R = resolvable::Simple;
+    type R = Simple<SimpleUnique>;
 
     let id = SimpleUnique { id: "id1" };
 
@@ -61,7 +82,7 @@ fn circular() {
         dependency: None
     };
 
-    let dep = resolvable::Simple::new(&circular);
+    let dep = Simple::new(&circular);
     circular.dependency = Some(dep);
 
     let path = Path::new(vec![]);
@@ -73,13 +94,88 @@ fn circular() {
     assert_eq!(res, expected);
 }
 
+#[test]
+fn circular_allowed() {
+    type R = Simple<SimpleUnique>;
+
+    let id = SimpleUnique { id: "id1" };
+
+    let mut circular: Node<R> = Node {
+        id: id.clone(),
+        dependency: None
+    };
+
+    let dep = Simple::new(&circular);
+    circular.dependency = Some(dep);
+
+    let recorder = Recorder::new();
+    let mut context = Context::first_solution()
+        .allowing_cycles()
+        .observed(recorder.clone());
+    let res = circular.solve_in(Path::new(vec![]), &mut context);
+
+    assert_eq!(res, Resolved::success(Path::new(vec![&circular]).closing(vec![id.clone(), id.clone()])));
+    assert_eq!(context.cycles(), &[vec![id.clone(), id.clone()]][..]);
+    assert_eq!(
+        recorder.events(),
+        vec![Event::Entered(id.clone()), Event::Cycle(id.clone()), Event::Solved(id, 1)]);
+
+    let iterative = circular.solve_iteratively(Path::new(vec![]), &mut Context::new().allowing_cycles());
+    assert_eq!(iterative, res);
+
+    let lazy: Vec<_> = circular.solutions(Path::new(vec![])).allowing_cycles().collect();
+    assert_eq!(lazy, res.paths);
+    assert_eq!(circular.solutions(Path::new(vec![])).count(), 0);
+}
+
+#[test]
+fn keeps_cycles_of_accepted_paths() {
+    type N = Node<OrAnd<SimpleUnique>>;
+
+    // a -> (b & x) | c, b -> a, x excludes a
+    let leaf = |name| N { id: SimpleUnique::new(name), dependency: None };
+    let b = N {
+        id: SimpleUnique::new("b"),
+        dependency: Some(OrAnd::new(OrDependency::single(AndDependency::single(leaf("a")))))
+    };
+    let x = N {
+        id: SimpleUnique::new("x"),
+        dependency: Some(OrAnd::excluding(OrDependency::new(vec![AndDependency::new(vec![])]), vec![SimpleUnique::new("a")]))
+    };
+    let a = N {
+        id: SimpleUnique::new("a"),
+        dependency: Some(OrAnd::new(OrDependency::new(vec![
+            AndDependency::new(vec![b, x]),
+            AndDependency::single(leaf("c"))])))
+    };
+
+    let cycle = vec![SimpleUnique::new("a"), SimpleUnique::new("b"), SimpleUnique::new("a")];
+    let ids = |res: &Resolved<OrAnd<SimpleUnique>>| res.paths
+        .iter()
+        .map(|path| (path.idents().into_iter().map(|id| id.id).collect::<Vec<_>>(), path.cycles.clone()))
+        .collect::<Vec<_>>();
+    let expected = vec![(vec!["a", "c"], vec![])];
+
+    let mut context = Context::new().allowing_cycles();
+    let res = a.solve_in(Path::new(vec![]), &mut context);
+    assert_eq!(ids(&res), expected);
+    // Only the rejected alternative closed the cycle
+    assert_eq!(context.cycles(), &[cycle][..]);
+
+    let iterative = a.solve_iteratively(Path::new(vec![]), &mut Context::new().allowing_cycles());
+    assert_eq!(iterative, res);
+
+    let lazy: Vec<_> = a.solutions(Path::new(vec![])).allowing_cycles().collect();
+    assert_eq!(lazy, res.paths);
+}
+
 #[test]
 fn recursive() {
     // c -> b -> a => c -> b -> a
-    type R = resolvable::Simple;
+    type R = Simple<SimpleUnique>;
 
     let nodes: Vec<Node<R>> =
-        vec!["a", "b", "c"]
+        ["a", "b", "c"]
         .iter()
         .map(|id| SimpleUnique { id })
         .map(|id| Node { id, dependency: None })
@@ -104,10 +200,10 @@ fn recursive() {
 fn with_any() {
     // a -> (b | c -> a) => a -> b
 
-    type R = resolvable::Any;
+    type R = Any<SimpleUnique>;
 
     let nodes: Vec<Node<R>> =
-        vec!["a", "b", "c"]
+        ["a", "b", "c"]
         .iter()
         .map(|id| SimpleUnique { id })
         .map(|id| Node { id, dependency: None })
@@ -134,15 +230,55 @@ fn with_any() {
     assert_eq!(res, expected);
 }
 
+#[test]
+fn observed_events() {
+    // a -> (b | c -> a)
+
+    type R = Any<SimpleUnique>;
+
+    let nodes: Vec<Node<R>> =
+        ["a", "b", "c"]
+        .iter()
+        .map(|id| SimpleUnique { id })
+        .map(|id| Node { id, dependency: None })
+        .collect();
+
+    let (mut a, b, mut c) = (nodes[0].clone(), nodes[1].clone(), nodes[2].clone());
+
+    c.dependency = Some(
+        R::new(vec![&a]));
+
+    a.dependency = Some(
+        R::new(vec![&b, &c]));
+
+    let recorder = Recorder::new();
+    let mut context = Context::new().observed(recorder.clone());
+    a.solve_in(Path::new(vec![]), &mut context);
+
+    let (a, b, c) = (a.id, b.id, c.id);
+
+    assert_eq!(
+        recorder.events(),
+        vec![
+            Event::Entered(a.clone()),
+            Event::Entered(b.clone()),
+            Event::Solved(b, 1),
+            Event::Entered(c.clone()),
+            Event::Entered(a.clone()),
+            Event::Conflict(a.clone()),
+            Event::Exhausted(c),
+            Event::Solved(a, 1)]);
+}
+
 #[test]
 fn with_orand() {
     // a -> (b | c -> d) => a -> c -> d
-    type R = resolvable::OrAnd;
-    type And = resolvable::AndDependency;
-    type Or = resolvable::OrDependency;
+    type R = OrAnd<SimpleUnique>;
+    type And = AndDependency<SimpleUnique>;
+    type Or = OrDependency<SimpleUnique>;
 
     let nodes: Vec<Node<R>> =
-        vec!["a", "b", "c", "d"]
+        ["a", "b", "c", "d"]
         .iter()
         .map(|id| SimpleUnique { id })
         .map(|id| Node { id, dependency: None })
@@ -184,3 +320,377 @@ fn with_orand() {
 
     assert_eq!(res, expected);
 }
+
+#[test]
+fn cached_diamonds() {
+    // n0 -> (l0 | r0), l0 -> n1, r0 -> n1, ..., n12 -> (nothing) => failure
+    type R = Any<SimpleUnique>;
+
+    let depth = 12;
+
+    let mut bottom: Node<R> = Node {
+        id: id(format!("n{}", depth)),
+        dependency: Some(R::new(vec![]))
+    };
+
+    for level in (0..depth).rev() {
+        let left = Node {
+            id: id(format!("l{}", level)),
+            dependency: Some(R::new(vec![&bottom]))
+        };
+        let right = Node {
+            id: id(format!("r{}", level)),
+            dependency: Some(R::new(vec![&bottom]))
+        };
+        bottom = Node {
+            id: id(format!("n{}", level)),
+            dependency: Some(R::new(vec![&left, &right]))
+        };
+    }
+
+    let mut context = Context::new();
+    let res = bottom.solve_in(Path::new(vec![]), &mut context);
+
+    assert_eq!(res, Resolved::failure(Cause::empty()));
+
+    // Every shared node is solved once through the left branch and reused
+    // through the right one
+    assert_eq!(context.cache.hits(), depth);
+}
+
+#[test]
+fn lazy_solutions() {
+    // a -> (b | c -> d) => a -> c -> d, same as `with_orand`
+    type R = OrAnd<SimpleUnique>;
+    type And = AndDependency<SimpleUnique>;
+    type Or = OrDependency<SimpleUnique>;
+
+    let nodes: Vec<Node<R>> =
+        ["a", "b", "c", "d"]
+        .iter()
+        .map(|id| SimpleUnique { id })
+        .map(|id| Node { id, dependency: None })
+        .collect();
+
+    let (mut a, mut b, mut c, d) = (nodes[0].clone(), nodes[1].clone(), nodes[2].clone(), nodes[3].clone());
+
+    c.dependency = Some(R::new(Or::single(And::single(d.clone()))));
+    b.dependency = Some(R::new(Or::single(And::new(vec![c.clone(), d.clone()]))));
+    a.dependency = Some(R::new(Or::new(vec![And::single(b.clone()), And::single(c.clone())])));
+
+    let lazy: Vec<_> = a.solutions(Path::new(vec![])).collect();
+    let eager = a.solve(Path::new(vec![]));
+
+    assert_eq!(lazy, eager.paths);
+}
+
+#[test]
+fn first_of_many_solutions() {
+    // root -> x0 & ... & x39, xi -> yi | zi => 2^40 solutions
+    type R = OrAnd<SimpleUnique>;
+    type And = AndDependency<SimpleUnique>;
+    type Or = OrDependency<SimpleUnique>;
+
+    let leaf = |name: String| Node { id: id(name), dependency: None };
+
+    let xs: Vec<Node<R>> = (0..40)
+        .map(|i| Node {
+            id: id(format!("x{}", i)),
+            dependency: Some(R::new(Or::new(vec![
+                And::single(leaf(format!("y{}", i))),
+                And::single(leaf(format!("z{}", i)))])))
+        })
+        .collect();
+
+    let root: Node<R> = Node {
+        id: id("root".to_string()),
+        dependency: Some(R::new(Or::single(And::new(xs))))
+    };
+
+    let mut solutions = root.solutions(Path::new(vec![]));
+
+    let first: Vec<_> = solutions.next().unwrap().idents();
+    assert_eq!(first.len(), 81);
+    assert!(first.iter().all(|ident| !ident.id.starts_with('z')));
+
+    let second: Vec<_> = solutions.next().unwrap().idents();
+    assert_eq!(second.last(), Some(&SimpleUnique { id: "z39" }));
+
+    let res = root.solve_in(Path::new(vec![]), &mut Context::first_solution());
+    assert_eq!(res.paths.len(), 1);
+    assert_eq!(res.paths[0].idents(), first);
+
+    let res = root.solve_in(Path::new(vec![]), &mut Context::new().max_solutions(3));
+    assert_eq!(res.paths.len(), 3);
+    assert_eq!(res.paths[1].idents(), second);
+}
+
+#[test]
+fn optimal_solutions() {
+    // a -> (b & c) | d | (e & f & g)
+    type R = OrAnd<SimpleUnique>;
+    type And = AndDependency<SimpleUnique>;
+    type Or = OrDependency<SimpleUnique>;
+
+    let leaf = |id| Node { id: SimpleUnique { id }, dependency: None };
+    let ids = |outcome: Outcome<R>| {
+        let resolved = outcome.finished().unwrap();
+        assert_eq!(resolved.paths.len(), 1);
+        resolved.paths[0].idents().into_iter().map(|ident| ident.id).collect::<Vec<_>>()
+    };
+
+    let a: Node<R> = Node {
+        id: SimpleUnique { id: "a" },
+        dependency: Some(R::new(Or::new(vec![
+            And::new(vec![leaf("b"), leaf("c")]),
+            And::single(leaf("d")),
+            And::new(vec![leaf("e"), leaf("f"), leaf("g")])])))
+    };
+
+    let installed = || ["a", "e", "f", "g"].iter().map(|&id| SimpleUnique { id });
+
+    let best = a.optimal(Path::new(vec![]), FewestPackages, &mut Context::new());
+    assert_eq!(ids(best), vec!["a", "d"]);
+
+    let best = a.optimal(Path::new(vec![]), FewestChanges::new(installed()), &mut Context::new());
+    assert_eq!(ids(best), vec!["a", "e", "f", "g"]);
+
+    let objective = Lexicographic::new(vec![
+        Box::new(FewestChanges::new(installed().take(1))),
+        Box::new(FewestPackages)]);
+    let best = a.optimal(Path::new(vec![]), objective, &mut Context::new());
+    assert_eq!(ids(best), vec!["a", "d"]);
+
+    // The first path is found, but not known to be the best
+    let best = a.optimal(Path::new(vec![]), FewestPackages, &mut Context::new().bounded(Budget::new().steps(2)));
+    assert!(best.is_aborted());
+}
+
+#[test]
+fn first_solution_failure() {
+    type R = Simple<SimpleUnique>;
+
+    let id = SimpleUnique { id: "id1" };
+
+    let mut circular: Node<R> = Node {
+        id: id.clone(),
+        dependency: None
+    };
+
+    let dep = Simple::new(&circular);
+    circular.dependency = Some(dep);
+
+    let res = circular.solve_in(Path::new(vec![]), &mut Context::first_solution());
+
+    assert_eq!(res, circular.solve(Path::new(vec![])));
+    assert!(!res.is_success());
+}
+
+#[test]
+fn limited_search_stops_early() {
+    // root -> l0 | ... | l49
+    type R = Any<SimpleUnique>;
+
+    let leaves: Vec<Node<R>> = (0..50)
+        .map(|i| Node { id: id(format!("l{}", i)), dependency: None })
+        .collect();
+    let root = Node {
+        id: SimpleUnique { id: "root" },
+        dependency: Some(R::new(leaves.iter().collect()))
+    };
+
+    let recorder = Recorder::new();
+    let mut context = Context::first_solution().observed(recorder.clone());
+    let res = root.solve_in(Path::new(vec![]), &mut context);
+
+    assert_eq!(res, Resolved::success(Path::new(vec![&root, &leaves[0]])));
+    assert_eq!(context.stats().steps, 2);
+    assert_eq!(
+        recorder.events(),
+        vec![
+            Event::Entered(root.id.clone()),
+            Event::Entered(leaves[0].id.clone()),
+            Event::Solved(leaves[0].id.clone(), 1),
+            Event::Solved(root.id.clone(), 1)]);
+
+    let mut context = Context::new().max_solutions(0);
+    assert_eq!(root.solve_in(Path::new(vec![]), &mut context), Resolved::failure(Cause::empty()));
+    assert_eq!(context.stats().steps, 0);
+
+    let mut context = Context::new().max_solutions(0);
+    assert!(root.solve_iteratively(Path::new(vec![]), &mut context).paths.is_empty());
+
+    // Failures are searched once, with the same cause as without a limit
+    let failing = failing_tree(6, &mut 0);
+    let (mut limited, mut full) = (Context::first_solution(), Context::new());
+
+    assert_eq!(
+        failing.solve_in(Path::new(vec![]), &mut limited),
+        failing.solve_in(Path::new(vec![]), &mut full));
+    assert_eq!(limited.stats().steps, full.stats().steps);
+}
+
+/// Binary tree of `Any` nodes of the given depth, whose leaves can't be
+/// satisfied.
+fn failing_tree(depth: usize, next: &mut usize) -> Node<Any<SimpleUnique>> {
+    let id = id(format!("n{}", next));
+    *next += 1;
+
+    let deps = match depth {
+        0 => vec![],
+        _ => vec![failing_tree(depth - 1, next), failing_tree(depth - 1, next)]
+    };
+
+    Node { id, dependency: Some(Any { deps }) }
+}
+
+#[test]
+fn aborts_on_budget() {
+    let root = failing_tree(12, &mut 0);
+
+    let mut context = Context::new().bounded(Budget::new().steps(1000));
+    match root.solve_within(Path::new(vec![]), &mut context) {
+        Outcome::Aborted(aborted) => {
+            assert_eq!(aborted.reason, Reason::Steps);
+            assert_eq!(aborted.stats.steps, 1000);
+        },
+        Outcome::Finished(_) => panic!("budget not enforced")
+    }
+
+    let mut context = Context::first_solution().bounded(Budget::new().steps(1000));
+    let outcome = root.solve_within(Path::new(vec![]), &mut context);
+    assert!(outcome.is_aborted());
+    match outcome.into_result() {
+        Err(Error::BudgetExceeded(aborted)) => assert_eq!(aborted.reason, Reason::Steps),
+        _ => panic!("budget not reported")
+    }
+
+    let mut context = Context::new().bounded(Budget::new().timeout(Duration::from_secs(0)));
+    match root.solve_within(Path::new(vec![]), &mut context) {
+        Outcome::Aborted(aborted) => assert_eq!(aborted.reason, Reason::Deadline),
+        Outcome::Finished(_) => panic!("deadline not enforced")
+    }
+}
+
+#[test]
+fn aborts_on_cancel() {
+    let root = failing_tree(4, &mut 0);
+    let cancelled = Arc::new(AtomicBool::new(false));
+
+    let canceller = {
+        let cancelled = cancelled.clone();
+        std::thread::spawn(move || cancelled.store(true, Ordering::Relaxed))
+    };
+    canceller.join().unwrap();
+
+    let mut context = Context::new().bounded(Budget::new().cancellable(cancelled));
+    match root.solve_within(Path::new(vec![]), &mut context) {
+        Outcome::Aborted(aborted) => {
+            assert_eq!(aborted.reason, Reason::Cancelled);
+            assert_eq!(aborted.stats.steps, 0);
+        },
+        Outcome::Finished(_) => panic!("cancellation ignored")
+    }
+}
+
+#[test]
+fn finishes_within_budget() {
+    let root = failing_tree(4, &mut 0);
+
+    let mut context = Context::new().bounded(Budget::new().steps(31));
+    let outcome = root.solve_within(Path::new(vec![]), &mut context);
+
+    // Unsatisfiable, which is not the same as aborted
+    assert_eq!(outcome, Outcome::Finished(root.solve(Path::new(vec![]))));
+    assert_eq!(context.stats().steps, 31);
+    assert!(context.aborted().is_none());
+}
+
+#[test]
+fn declared_conflicts() {
+    // root -> x | y, x -> lib, y -> lib, lib -> old | new, x conflicts with old
+    type R = OrAnd<SimpleUnique>;
+    type And = AndDependency<SimpleUnique>;
+    type Or = OrDependency<SimpleUnique>;
+
+    let leaf = |id| Node { id: SimpleUnique { id }, dependency: None };
+    let (old, new) = (leaf("old"), leaf("new"));
+
+    let lib: Node<R> = Node {
+        id: SimpleUnique { id: "lib" },
+        dependency: Some(R::new(Or::new(vec![And::single(old.clone()), And::single(new.clone())])))
+    };
+    let x = Node {
+        id: SimpleUnique { id: "x" },
+        dependency: Some(R::excluding(Or::single(And::single(lib.clone())), vec![old.id.clone()]))
+    };
+    let y = Node {
+        id: SimpleUnique { id: "y" },
+        dependency: Some(R::new(Or::single(And::single(lib.clone()))))
+    };
+    let root = Node {
+        id: SimpleUnique { id: "root" },
+        dependency: Some(R::new(Or::new(vec![And::single(x.clone()), And::single(y.clone())])))
+    };
+
+    let res = root.solve(Path::new(vec![]));
+    let solved: Vec<Vec<_>> = res.paths
+        .iter()
+        .map(|path| path.idents().into_iter().map(|id| id.id).collect())
+        .collect();
+    assert_eq!(solved, vec![
+        vec!["root", "x", "lib", "new"],
+        vec!["root", "y", "lib", "old"],
+        vec!["root", "y", "lib", "new"]]);
+
+    // The cache keeps apart the prefixes declaring conflicts
+    let lazy: Vec<_> = root.solutions(Path::new(vec![])).collect();
+    assert_eq!(lazy, res.paths);
+    assert_eq!(root.solve_iteratively(Path::new(vec![]), &mut Context::new()), res);
+
+    // Failures are caused by the declaring node
+    let only_old: Node<R> = Node {
+        id: SimpleUnique { id: "lib" },
+        dependency: Some(R::new(Or::single(And::single(old.clone()))))
+    };
+    let res = only_old.solve(Path::new(vec![&x]));
+    assert_eq!(res, Resolved::failure(Cause::from(&x)));
+    assert_eq!(only_old.solve_iteratively(Path::new(vec![&x]), &mut Context::new()), res);
+}
+
+#[test]
+fn explains_failures() {
+    // root -> (x & y) | z, x -> w, y -> v excluding w, z -> root
+    type R = OrAnd<SimpleUnique>;
+    type And = AndDependency<SimpleUnique>;
+    type Or = OrDependency<SimpleUnique>;
+
+    let leaf = |name: &'static str| Node { id: SimpleUnique::new(name), dependency: None };
+    let (w, v) = (leaf("w"), leaf("v"));
+
+    let x = Node { id: SimpleUnique::new("x"), dependency: Some(R::new(Or::single(And::single(w.clone())))) };
+    let y = Node {
+        id: SimpleUnique::new("y"),
+        dependency: Some(R::excluding(Or::single(And::single(v)), vec![w.id.clone()]))
+    };
+    let z = Node { id: SimpleUnique::new("z"), dependency: Some(R::new(Or::single(And::single(leaf("root"))))) };
+
+    let root = Node {
+        id: SimpleUnique::new("root"),
+        dependency: Some(R::new(Or::new(vec![And::new(vec![x, y]), And::single(z)])))
+    };
+
+    assert_eq!(
+        root.explain().unwrap().to_string(),
+        "1. root is requested\n\
+         2. root depends on x and y, or z\n\
+         3. y conflicts with w\n\
+         4. Because of 3, x and y can't both be selected\n\
+         5. z depends on root\n\
+         6. root can only be selected once\n\
+         7. Because of 5 and 6, z and root can't both be selected\n\
+         8. Because of 2, 4 and 7, root can't be selected\n\
+         9. Because of 1 and 8, there is no solution\n");
+
+    assert_eq!(w.explain(), None);
+}
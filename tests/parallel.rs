@@ -0,0 +1,168 @@
+extern crate rosol;
+
+mod common;
+
+use rosol::budget::Budget;
+use rosol::budget::Reason;
+use rosol::context::Context;
+use rosol::node::Node;
+use rosol::node::cause::Cause;
+use rosol::node::resolved::Resolved;
+use rosol::observer::Event;
+use rosol::observer::Recorder;
+use rosol::package::ident::SimpleUnique;
+use rosol::path::Path;
+
+use rosol::resolvables::AndDependency;
+use rosol::resolvables::OrAnd;
+use rosol::resolvables::OrDependency;
+
+use common::id;
+use common::random_graph;
+
+type R = OrAnd<SimpleUnique>;
+type And = AndDependency<SimpleUnique>;
+type Or = OrDependency<SimpleUnique>;
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn shares_across_threads() {
+    assert_send_sync::<Node<R>>();
+    assert_send_sync::<Path<'static, R>>();
+    assert_send_sync::<Cause<'static, R>>();
+    assert_send_sync::<Resolved<'static, R>>();
+}
+
+/// root -> x0 | ... | x(width - 1), xi -> (yi & z) | wi, z conflicting
+/// with the root for even i
+fn wide(width: usize) -> Node<R> {
+    let leaf = |name: String| Node { id: id(name), dependency: None };
+
+    let xs = (0..width)
+        .map(|i| {
+            let z = match i % 2 {
+                0 => leaf("root".to_string()),
+                _ => leaf("z".to_string())
+            };
+
+            And::single(Node {
+                id: id(format!("x{}", i)),
+                dependency: Some(R::new(Or::new(vec![
+                    And::new(vec![leaf(format!("y{}", i)), z]),
+                    And::single(leaf(format!("w{}", i)))])))
+            })
+        })
+        .collect();
+
+    Node {
+        id: id("root".to_string()),
+        dependency: Some(R::new(Or::new(xs)))
+    }
+}
+
+#[test]
+fn matches_sequential() {
+    let root = wide(16);
+    let sequential = root.solve(Path::new(vec![]));
+    assert_eq!(sequential.paths.len(), 24);
+
+    for workers in 1..6 {
+        let parallel = root.solve_parallel(Path::new(vec![]), &mut Context::new(), workers);
+        assert_eq!(parallel, sequential);
+    }
+}
+
+#[test]
+fn matches_sequential_on_random_graphs() {
+    for seed in 0..200 {
+        let root = random_graph(9, seed);
+        let sequential = root.solve(Path::new(vec![]));
+
+        for &workers in [2, 3].iter() {
+            let parallel = root.solve_parallel(Path::new(vec![]), &mut Context::new(), workers);
+            assert_eq!(parallel, sequential, "seed {}", seed);
+        }
+    }
+}
+
+#[test]
+fn honours_limit() {
+    let root = wide(16);
+
+    let parallel = root.solve_parallel(Path::new(vec![]), &mut Context::new().max_solutions(3), 4);
+    let sequential = root.solve_in(Path::new(vec![]), &mut Context::new().max_solutions(3));
+
+    assert_eq!(parallel, sequential);
+}
+
+#[test]
+fn keeps_causes_with_limit() {
+    // root -> x | b | d, x -> c, c excludes the root
+    let leaf = |name: &'static str| Node { id: SimpleUnique::new(name), dependency: None };
+    let c = Node {
+        id: SimpleUnique::new("c"),
+        dependency: Some(R::excluding(Or::new(vec![And::new(vec![])]), vec![SimpleUnique::new("root")]))
+    };
+    let x = Node { id: SimpleUnique::new("x"), dependency: Some(R::new(Or::single(And::single(c.clone())))) };
+    let root = Node {
+        id: SimpleUnique::new("root"),
+        dependency: Some(R::new(Or::new(vec![And::single(x), And::single(leaf("b")), And::single(leaf("d"))])))
+    };
+
+    let parallel = root.solve_parallel(Path::new(vec![]), &mut Context::first_solution(), 3);
+    let sequential = root.solve_in(Path::new(vec![]), &mut Context::first_solution());
+
+    assert_eq!(sequential.cause, Cause::from(&c));
+    assert_eq!(parallel, sequential);
+}
+
+#[test]
+fn stops_early_with_limit() {
+    let root = wide(64);
+
+    let mut full = Context::new();
+    let all = root.solve_parallel(Path::new(vec![]), &mut full, 2);
+
+    let steps: Vec<_> = (0..10)
+        .map(|_| {
+            let mut context = Context::new().max_solutions(1);
+            let parallel = root.solve_parallel(Path::new(vec![]), &mut context, 2);
+            assert_eq!(parallel.paths, all.paths[..1].to_vec());
+            context.stats().steps
+        })
+        .collect();
+
+    assert!(steps.iter().all(|&steps| steps <= full.stats().steps));
+    assert!(steps.iter().any(|&steps| steps < full.stats().steps));
+}
+
+#[test]
+fn shares_the_budget_between_workers() {
+    let root = wide(64);
+
+    let mut context = Context::new().bounded(Budget::new().steps(100));
+    root.solve_parallel(Path::new(vec![]), &mut context, 4);
+
+    assert_eq!(context.aborted().map(|aborted| aborted.reason), Some(Reason::Steps));
+    assert!(context.stats().steps <= 100, "took {} steps", context.stats().steps);
+}
+
+#[test]
+fn observes_workers() {
+    let root = wide(8);
+    let recorder = Recorder::new();
+
+    let mut context = Context::new().observed(recorder.clone());
+    let res = root.solve_parallel(Path::new(vec![]), &mut context, 4);
+
+    let events = recorder.events();
+    assert_eq!(events.first(), Some(&Event::Entered(root.id.clone())));
+    assert_eq!(events.last(), Some(&Event::Solved(root.id.clone(), res.paths.len())));
+
+    let entered = events
+        .iter()
+        .filter(|event| matches!(event, Event::Entered(_)))
+        .count();
+    assert_eq!(context.stats().steps, entered);
+}
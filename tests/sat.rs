@@ -0,0 +1,306 @@
+extern crate rosol;
+
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+
+use rosol::explanation::Incompatibility;
+use rosol::node::Node;
+use rosol::package::dependency::Dependency;
+use rosol::package::feature::Feature;
+use rosol::package::ident::SimpleUnique;
+use rosol::package::marker::Environment;
+use rosol::package::package::Package;
+use rosol::package::repository::Repository;
+use rosol::path::Path;
+use rosol::resolvables::AndDependency;
+use rosol::resolvables::OrAnd;
+use rosol::resolvables::OrDependency;
+use rosol::sat;
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct Pkg {
+    id: SimpleUnique,
+    dependencies: Vec<Dependency<SimpleUnique>>,
+    provides: Vec<String>,
+    features: Vec<(String, Feature<SimpleUnique>)>
+}
+
+impl Pkg {
+    fn new(id: &'static str, dependencies: Vec<Dependency<SimpleUnique>>) -> Self {
+        Pkg { id: SimpleUnique::new(id), dependencies, provides: vec![], features: vec![] }
+    }
+
+    fn providing(mut self, name: &str) -> Self {
+        self.provides.push(name.to_string());
+        self
+    }
+
+    fn feature(mut self, name: &str, dependencies: Vec<Dependency<SimpleUnique>>, enables: &[&str]) -> Self {
+        self.features.push((name.to_string(), Feature::new(dependencies, enables)));
+        self
+    }
+}
+
+impl Package for Pkg {
+    type Id = SimpleUnique;
+
+    fn id(&self) -> SimpleUnique {
+        self.id.clone()
+    }
+
+    fn dependencies(&self) -> Vec<Dependency<SimpleUnique>> {
+        self.dependencies.clone()
+    }
+
+    fn provides(&self) -> Vec<String> {
+        self.provides.clone()
+    }
+
+    fn features(&self) -> HashMap<String, Feature<SimpleUnique>> {
+        self.features.iter().cloned().collect()
+    }
+}
+
+#[test]
+fn agrees_with_dfs() {
+    // a -> (b | c), b -> c & d, c -> d
+    type R = OrAnd<SimpleUnique>;
+    type And = AndDependency<SimpleUnique>;
+    type Or = OrDependency<SimpleUnique>;
+
+    let (a, b, c, d) = (
+        SimpleUnique::new("a"), SimpleUnique::new("b"),
+        SimpleUnique::new("c"), SimpleUnique::new("d"));
+
+    let repo = Repository::new(vec![
+        Pkg::new("a", vec![Dependency::Choice(vec![b.clone(), c.clone()])]),
+        Pkg::new("b", vec![Dependency::Choice(vec![c.clone()]), Dependency::Choice(vec![d.clone()])]),
+        Pkg::new("c", vec![Dependency::Choice(vec![d.clone()])]),
+        Pkg::new("d", vec![])]);
+
+    let d_: Node<R> = Node { id: d.clone(), dependency: None };
+    let c_: Node<R> = Node { id: c.clone(), dependency: Some(R::new(Or::single(And::single(d_.clone())))) };
+    let b_: Node<R> = Node { id: b.clone(), dependency: Some(R::new(Or::single(And::new(vec![c_.clone(), d_.clone()])))) };
+    let a_: Node<R> = Node { id: a.clone(), dependency: Some(R::new(Or::new(vec![And::single(b_), And::single(c_)]))) };
+
+    let dfs = a_.solve(Path::new(vec![]));
+
+    assert_eq!(
+        sat::solve(&repo, &a),
+        Some(dfs.paths[0].idents()));
+}
+
+#[test]
+fn selects_one_exclusive_provider() {
+    // app -> mta, app -> tool, tool -> postfix, exim and postfix provide
+    // mta exclusively
+    let mta = || Dependency::provider("mta");
+    let exclusive = || Dependency::exclusive("mta");
+
+    let repo = Repository::new(vec![
+        Pkg::new("app", vec![mta(), Dependency::Choice(vec![SimpleUnique::new("tool")])]),
+        Pkg::new("tool", vec![Dependency::Choice(vec![SimpleUnique::new("postfix")])]),
+        Pkg::new("exim", vec![exclusive()]).providing("mta"),
+        Pkg::new("postfix", vec![exclusive()]).providing("mta"),
+        Pkg::new("both", vec![Dependency::Choice(vec![SimpleUnique::new("exim")]), Dependency::Choice(vec![SimpleUnique::new("postfix")])])]);
+
+    assert_eq!(
+        sat::solve(&repo, &SimpleUnique::new("app")),
+        Some(vec![SimpleUnique::new("app"), SimpleUnique::new("postfix"), SimpleUnique::new("tool")]));
+
+    assert_eq!(sat::solve(&repo, &SimpleUnique::new("both")), None);
+    let explanation = sat::explain(&repo, &SimpleUnique::new("both")).unwrap().unwrap();
+    assert!(explanation.incompatibilities.contains(
+        &Incompatibility::Conflict(SimpleUnique::new("postfix"), SimpleUnique::new("exim"))));
+}
+
+fn choice(id: &'static str) -> Dependency<SimpleUnique> {
+    Dependency::Choice(vec![SimpleUnique::new(id)])
+}
+
+fn features_repo() -> Repository<Pkg> {
+    // app -> lib[json], app -> tool, tool -> lib[yaml], lib[json] -> serde
+    // and turns on lib[std], lib[yaml] -> yaml, lib[xml] -> heavy, broken ->
+    // lib[toml], strict -> lib[xml], strict conflicts with heavy
+    Repository::new(vec![
+        Pkg::new("app", vec![choice("lib").with_features(&["json"]), choice("tool")]),
+        Pkg::new("tool", vec![choice("lib").with_features(&["yaml"])]),
+        Pkg::new("lib", vec![])
+            .feature("json", vec![choice("serde")], &["std"])
+            .feature("yaml", vec![choice("yaml")], &[])
+            .feature("xml", vec![choice("heavy")], &[])
+            .feature("std", vec![], &[]),
+        Pkg::new("serde", vec![]),
+        Pkg::new("yaml", vec![]),
+        Pkg::new("heavy", vec![]),
+        Pkg::new("broken", vec![choice("lib").with_features(&["toml"])]),
+        Pkg::new("strict", vec![choice("lib").with_features(&["xml"]), Dependency::conflicts(choice("heavy"))])])
+}
+
+#[test]
+fn unifies_features() {
+    let repo = features_repo();
+    let selection = sat::select(&repo, &SimpleUnique::new("app")).unwrap();
+
+    let ids: Vec<_> = selection.ids.iter().map(|id| id.id).collect();
+    assert_eq!(ids, vec!["app", "lib", "serde", "tool", "yaml"]);
+
+    let lib: Vec<_> = selection.features[&SimpleUnique::new("lib")].iter().cloned().collect();
+    assert_eq!(lib, vec!["json", "std", "yaml"]);
+    assert_eq!(selection.features[&SimpleUnique::new("tool")], BTreeSet::new());
+}
+
+#[test]
+fn requests_root_features() {
+    let repo = features_repo();
+    let selection = sat::select_with_features(&repo, &SimpleUnique::new("lib"), &["xml"]).unwrap();
+
+    assert_eq!(selection.ids, vec![SimpleUnique::new("lib"), SimpleUnique::new("heavy")]);
+    assert_eq!(
+        selection.features[&SimpleUnique::new("lib")],
+        vec!["xml".to_string()].into_iter().collect());
+
+    assert_eq!(sat::select_with_features(&repo, &SimpleUnique::new("lib"), &["toml"]), None);
+}
+
+#[test]
+fn explains_missing_features() {
+    let repo = features_repo();
+
+    assert_eq!(sat::solve(&repo, &SimpleUnique::new("broken")), None);
+    assert_eq!(
+        sat::explain(&repo, &SimpleUnique::new("broken")).unwrap().unwrap().to_string(),
+        "1. broken is requested\n\
+         2. lib has no feature toml\n\
+         3. broken depends on lib\n\
+         4. Because of 1, 2 and 3, there is no solution\n");
+
+    assert_eq!(
+        sat::explain(&repo, &SimpleUnique::new("strict")).unwrap().unwrap().to_string(),
+        "1. strict is requested\n\
+         2. strict turns on feature xml of lib\n\
+         3. strict depends on lib\n\
+         4. strict conflicts with heavy\n\
+         5. lib depends on heavy\n\
+         6. Because of 1, 2, 3, 4 and 5, there is no solution\n");
+}
+
+#[test]
+fn keeps_dependencies_applying_in_environment() {
+    let marker = |input: &str| input.parse().unwrap();
+
+    // app needs a tls backend, native on windows and openssl elsewhere,
+    // colors on terminals that aren't dumb, and recommends the runtime;
+    // openssl breaks old runtimes
+    let repo = Repository::new(vec![
+        Pkg::new("app", vec![
+            choice("native-tls").when(marker("os == 'windows'")),
+            choice("openssl").when(marker("os != 'windows'")),
+            Dependency::recommends(choice("colors")).when(marker("not term == 'dumb'")),
+            Dependency::recommends(choice("runtime"))]),
+        Pkg::new("native-tls", vec![]),
+        Pkg::new("openssl", vec![Dependency::breaks(choice("runtime")).when(marker("runtime < '3'"))]),
+        Pkg::new("colors", vec![]),
+        Pkg::new("runtime", vec![])]);
+
+    let ids = |environment: Environment| -> Vec<&'static str> {
+        let request = sat::Request::new().environment(environment);
+        sat::select_for(&repo, &SimpleUnique::new("app"), &request)
+            .unwrap()
+            .ids
+            .iter()
+            .map(|id| id.id)
+            .collect()
+    };
+
+    assert_eq!(ids(Environment::new().with("os", "windows")), vec!["app", "native-tls", "colors", "runtime"]);
+    assert_eq!(ids(Environment::new().with("os", "linux").with("term", "dumb")), vec!["app", "openssl"]);
+
+    // Only an old runtime is broken, and only where openssl is needed. An
+    // unset runtime compares as older than any.
+    assert_eq!(
+        ids(Environment::new().with("os", "linux").with("term", "dumb").with("runtime", "2")),
+        vec!["app", "openssl"]);
+    assert_eq!(
+        ids(Environment::new().with("os", "linux").with("term", "dumb").with("runtime", "3")),
+        vec!["app", "openssl", "runtime"]);
+    assert_eq!(
+        ids(Environment::new().with("os", "windows").with("term", "dumb").with("runtime", "2")),
+        vec!["app", "native-tls", "runtime"]);
+    assert_eq!(
+        sat::solve(&repo, &SimpleUnique::new("app")).unwrap(),
+        ["app", "native-tls", "openssl", "colors"].iter().map(|&id| SimpleUnique::new(id)).collect::<Vec<_>>());
+}
+
+#[test]
+fn forks_diverging_environments() {
+    let marker = |input: &str| input.parse().unwrap();
+
+    // app needs openssl-3 on linux, openssl-1 on macos, which conflict, and
+    // winapi on windows
+    let repo = Repository::new(vec![
+        Pkg::new("app", vec![
+            choice("openssl-3").when(marker("os == 'linux'")),
+            choice("openssl-1").when(marker("os == 'macos'")),
+            choice("winapi").when(marker("os == 'windows'"))]),
+        Pkg::new("openssl-3", vec![Dependency::conflicts(choice("openssl-1"))]),
+        Pkg::new("openssl-1", vec![]),
+        Pkg::new("winapi", vec![])]);
+
+    let os = |name: &str| Environment::new().with("os", name);
+    let names = |ids: &[SimpleUnique]| -> Vec<&'static str> { ids.iter().map(|id| id.id).collect() };
+
+    let request = sat::Request::new()
+        .environment(os("linux"))
+        .environment(os("macos"))
+        .environment(os("windows"));
+    let forks = sat::universal(&repo, &SimpleUnique::new("app"), &request).unwrap();
+
+    assert_eq!(forks.len(), 2);
+    assert_eq!(forks[0].environments, vec![os("linux"), os("windows")]);
+    assert_eq!(names(&forks[0].selection.ids), vec!["app", "openssl-3", "winapi"]);
+    assert_eq!(names(&forks[0].installs[0]), vec!["app", "openssl-3"]);
+    assert_eq!(names(&forks[0].installs[1]), vec!["app", "winapi"]);
+    assert_eq!(forks[1].environments, vec![os("macos")]);
+    assert_eq!(names(&forks[1].selection.ids), vec!["app", "openssl-1"]);
+
+    assert_eq!(sat::select_for(&repo, &SimpleUnique::new("app"), &request), None);
+
+    let single = sat::universal(&repo, &SimpleUnique::new("app"), &sat::Request::new().environment(os("macos"))).unwrap();
+    assert_eq!(single.len(), 1);
+}
+
+#[test]
+fn scopes_exclusions_to_their_environments() {
+    let marker = |input: &str| input.parse().unwrap();
+
+    // app needs legacy on linux and shim everywhere, shim breaks legacy on
+    // windows only
+    let repo = Repository::new(vec![
+        Pkg::new("app", vec![choice("legacy").when(marker("os == 'linux'")), choice("shim")]),
+        Pkg::new("legacy", vec![]),
+        Pkg::new("shim", vec![Dependency::breaks(choice("legacy")).when(marker("os == 'windows'"))])]);
+
+    let os = |name: &str| Environment::new().with("os", name);
+    let names = |ids: &[SimpleUnique]| -> Vec<&'static str> { ids.iter().map(|id| id.id).collect() };
+    let request = sat::Request::new()
+        .environment(os("linux"))
+        .environment(os("windows"));
+
+    let selection = sat::select_for(&repo, &SimpleUnique::new("app"), &request).unwrap();
+    assert_eq!(names(&selection.ids), vec!["app", "legacy", "shim"]);
+
+    let forks = sat::universal(&repo, &SimpleUnique::new("app"), &request).unwrap();
+    assert_eq!(forks.len(), 1);
+    assert_eq!(names(&forks[0].installs[0]), vec!["app", "legacy", "shim"]);
+    assert_eq!(names(&forks[0].installs[1]), vec!["app", "shim"]);
+
+    // Where both are installed, they still can't be
+    let repo = Repository::new(vec![
+        Pkg::new("app", vec![choice("legacy"), choice("shim")]),
+        Pkg::new("legacy", vec![]),
+        Pkg::new("shim", vec![Dependency::breaks(choice("legacy")).when(marker("os == 'windows'"))])]);
+
+    assert_eq!(sat::select_for(&repo, &SimpleUnique::new("app"), &request), None);
+    assert!(sat::select_for(&repo, &SimpleUnique::new("app"), &sat::Request::new().environment(os("linux"))).is_some());
+}